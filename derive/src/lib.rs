@@ -0,0 +1,259 @@
+//! Derive macros for [tiny_frame](https://docs.rs/tiny_frame).
+//!
+//! These are re-exported by `tiny_frame` when its `derive` feature is enabled.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Fields, Index, Path};
+
+/// Derives `TinyFrameMessage` for a struct.
+///
+/// The frame format and message type are given with the `tiny_frame` attribute. Fields are
+/// packed in declaration order using big endian encoding, and must implement `BufferWritable`,
+/// `BufferReadable` and `Default`.
+///
+/// # Examples
+/// ```
+/// use tiny_frame::*;
+///
+/// #[derive(Debug, PartialEq, TinyFrameMessage)]
+/// #[tiny_frame(format = DefaultFormat, msg_type = 0x20)]
+/// struct Reading {
+///     channel: u8,
+///     value: i32,
+/// }
+///
+/// #[derive(Debug, PartialEq, TinyFrameMessage)]
+/// #[tiny_frame(format = DefaultFormat, msg_type = 0x21)]
+/// struct Ping;
+///
+/// let msg = Reading { channel: 2, value: -2 }.to_msg();
+/// assert_eq!(msg.msg_type, 0x20);
+/// assert_eq!(msg.data, [2, 0xff, 0xff, 0xff, 0xfe]);
+/// assert_eq!(Reading::from_msg(&msg), Ok(Reading { channel: 2, value: -2 }));
+///
+/// assert_eq!(Ping::from_msg(&msg), Err(message::MessageError::WrongType));
+/// ```
+#[proc_macro_derive(TinyFrameMessage, attributes(tiny_frame))]
+pub fn derive_tiny_frame_message(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_message(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `FrameType` for a fieldless enum with a `#[repr]` integer type.
+///
+/// The enum must also derive `Clone`, `Copy` and `PartialEq`.
+///
+/// # Examples
+/// ```
+/// use tiny_frame::number::FrameType;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, tiny_frame::FrameType)]
+/// #[repr(u16)]
+/// enum Command {
+///     Ping = 1,
+///     Reset,
+///     Status = 0x100,
+/// }
+///
+/// assert_eq!(Command::Status.to_raw(), 0x100);
+/// assert_eq!(Command::try_from_raw(2), Ok(Command::Reset));
+/// assert_eq!(Command::try_from_raw(3), Err(3));
+/// ```
+///
+/// Pointer-sized and 128-bit reprs are rejected, because they have no fixed wire size:
+/// ```compile_fail
+/// #[derive(Debug, Clone, Copy, PartialEq, tiny_frame::FrameType)]
+/// #[repr(usize)] // error: `#[repr(usize)]` cannot be used as a frame type
+/// enum Command {
+///     Ping = 1,
+/// }
+/// ```
+#[proc_macro_derive(FrameType)]
+pub fn derive_frame_type(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_frame_type(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_message(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let mut format: Option<Path> = None;
+    let mut msg_type: Option<Expr> = None;
+
+    for attr in &input.attrs {
+        if !attr.path().is_ident("tiny_frame") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("format") {
+                format = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("msg_type") {
+                msg_type = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `format` or `msg_type`"))
+            }
+        })?;
+    }
+
+    let format = format.ok_or_else(|| {
+        Error::new(
+            Span::call_site(),
+            "missing `#[tiny_frame(format = ...)]` attribute",
+        )
+    })?;
+    let msg_type = msg_type.ok_or_else(|| {
+        Error::new(
+            Span::call_site(),
+            "missing `#[tiny_frame(msg_type = ...)]` attribute",
+        )
+    })?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "TinyFrameMessage can only be derived for structs",
+            ))
+        }
+    };
+
+    let write_fields = fields.iter().enumerate().map(|(i, field)| {
+        let member = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = Index::from(i);
+                quote!(#index)
+            }
+        };
+        quote! {
+            ::tiny_frame::message::write_field(&self.#member, buf);
+        }
+    });
+
+    let read_fields = fields.iter().map(|field| {
+        let ty = &field.ty;
+        quote!(::tiny_frame::message::read_field::<#ty>(buf)?)
+    });
+
+    let construct = match fields {
+        Fields::Named(_) => {
+            let names = fields.iter().map(|field| &field.ident);
+            quote!(Self { #(#names: #read_fields,)* })
+        }
+        Fields::Unnamed(_) => quote!(Self(#(#read_fields,)*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::tiny_frame::message::TinyFrameMessage<#format>
+            for #name #ty_generics #where_clause
+        {
+            const MSG_TYPE: <#format as ::tiny_frame::FrameFormat>::Type = #msg_type;
+
+            fn write_payload(&self, buf: &mut ::tiny_frame::__private::Vec<u8>) {
+                #(#write_fields)*
+            }
+
+            fn read_payload(
+                buf: &mut &[u8],
+            ) -> ::core::result::Result<Self, ::tiny_frame::message::MessageError> {
+                ::core::result::Result::Ok(#construct)
+            }
+        }
+    })
+}
+
+/// Integer reprs that implement `FrameType`.
+const FRAME_TYPE_REPRS: &[&str] = &["u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64"];
+
+/// Integer reprs that are valid Rust but cannot be frame types.
+const OTHER_INTEGER_REPRS: &[&str] = &["u128", "i128", "usize", "isize"];
+
+fn expand_frame_type(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let mut repr = None;
+
+    for attr in &input.attrs {
+        if attr.path().is_ident("repr") {
+            attr.parse_nested_meta(|meta| {
+                if let Some(ident) = meta.path.get_ident() {
+                    let name = ident.to_string();
+                    if FRAME_TYPE_REPRS.contains(&name.as_str()) {
+                        repr = Some(ident.clone());
+                    } else if OTHER_INTEGER_REPRS.contains(&name.as_str()) {
+                        return Err(Error::new(
+                            ident.span(),
+                            format!(
+                                "`#[repr({})]` cannot be used as a frame type; \
+                                 use one of u8, u16, u32, u64, i8, i16, i32 or i64",
+                                name
+                            ),
+                        ));
+                    }
+                }
+                Ok(())
+            })?;
+        }
+    }
+
+    let repr = repr.ok_or_else(|| {
+        Error::new(
+            Span::call_site(),
+            "FrameType can only be derived for enums with an integer `#[repr]`",
+        )
+    })?;
+
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "FrameType can only be derived for enums",
+            ))
+        }
+    };
+
+    for variant in variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new(
+                variant.fields.span(),
+                "FrameType can only be derived for enums without fields",
+            ));
+        }
+    }
+
+    let name = &input.ident;
+    let variant_names = variants.iter().map(|variant| &variant.ident);
+
+    Ok(quote! {
+        impl ::tiny_frame::number::FrameType for #name {
+            type Raw = #repr;
+
+            fn to_raw(self) -> #repr {
+                self as #repr
+            }
+
+            fn try_from_raw(raw: #repr) -> ::core::result::Result<Self, #repr> {
+                #(
+                    if raw == #name::#variant_names as #repr {
+                        return ::core::result::Result::Ok(#name::#variant_names);
+                    }
+                )*
+                ::core::result::Result::Err(raw)
+            }
+        }
+    })
+}
@@ -0,0 +1,289 @@
+#ifndef TINY_FRAME_H
+#define TINY_FRAME_H
+
+/* This file is generated by cbindgen. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Peer bit.
+ */
+typedef enum {
+  TF_SLAVE = 0,
+  TF_MASTER = 1,
+} TF_Peer;
+
+/**
+ * Listener result.
+ */
+typedef enum {
+  /**
+   * Not handled, pass on to the next listener.
+   */
+  TF_NEXT = 0,
+  /**
+   * Handled, keep the listener.
+   */
+  TF_STAY = 1,
+  /**
+   * Handled, keep the listener and restart its timeout.
+   */
+  TF_RENEW = 2,
+  /**
+   * Handled, remove the listener.
+   */
+  TF_CLOSE = 3,
+} TF_Result;
+
+/**
+ * A TinyFrame instance.
+ *
+ * Listeners are kept in slots that are cleared rather than removed, so that listeners may be
+ * added and removed from within listener callbacks.
+ */
+typedef struct TinyFrame TinyFrame;
+
+/**
+ * Frame ID type.
+ */
+typedef uint8_t TF_ID;
+
+/**
+ * Frame type type.
+ */
+typedef uint8_t TF_TYPE;
+
+/**
+ * Frame length type.
+ */
+typedef uint16_t TF_LEN;
+
+/**
+ * A message passed to and from the C API.
+ */
+typedef struct {
+  TF_ID frame_id;
+  bool is_response;
+  TF_TYPE type;
+  const uint8_t *data;
+  TF_LEN len;
+  void *userdata;
+  void *userdata2;
+} TF_Msg;
+
+/**
+ * A message listener.
+ */
+typedef TF_Result (*TF_Listener)(TinyFrame *tf, TF_Msg *msg);
+
+/**
+ * A listener timeout callback.
+ */
+typedef TF_Result (*TF_Listener_Timeout)(TinyFrame *tf);
+
+/**
+ * Tick counter type used for timeouts.
+ */
+typedef uint16_t TF_TICKS;
+
+/**
+ * Writes encoded bytes to the transport. Must be defined by the C side.
+ */
+extern void TF_WriteImpl(TinyFrame *tf, const uint8_t *buff, uint32_t len);
+
+/**
+ * Creates a new TinyFrame instance.
+ */
+TinyFrame *TF_Init(TF_Peer peer_bit);
+
+/**
+ * Frees a TinyFrame instance.
+ *
+ * # Safety
+ * `tf` must have been returned by [TF_Init] and must not be used afterwards.
+ */
+void TF_DeInit(TinyFrame *tf);
+
+/**
+ * Sets the user data pointer of the instance.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+void TF_SetUserdata(TinyFrame *tf, void *userdata, uint32_t usertag);
+
+/**
+ * Returns the user data pointer of the instance.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+void *TF_GetUserdata(TinyFrame *tf);
+
+/**
+ * Returns the user tag of the instance.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+uint32_t TF_GetUsertag(TinyFrame *tf);
+
+/**
+ * Resets the message parser, discarding any partially received frame.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+void TF_ResetParser(TinyFrame *tf);
+
+/**
+ * Accepts received bytes. Listeners are called for every complete frame.
+ *
+ * # Safety
+ * `tf` must be a valid instance and `buffer` must point to `count` readable bytes.
+ */
+void TF_Accept(TinyFrame *tf, const uint8_t *buffer, uint32_t count);
+
+/**
+ * Accepts a single received byte.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+void TF_AcceptChar(TinyFrame *tf, uint8_t c);
+
+/**
+ * Adds a listener for messages with the ID of the given message. `timeout` is the number of
+ * ticks after which the listener is removed, or 0 for no timeout.
+ *
+ * # Safety
+ * `tf` must be a valid instance and `msg` must point to a valid message.
+ */
+bool TF_AddIdListener(TinyFrame *tf,
+                      TF_Msg *msg,
+                      TF_Listener cb,
+                      TF_Listener_Timeout ftimeout,
+                      TF_TICKS timeout);
+
+/**
+ * Removes the listener for the given ID.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+bool TF_RemoveIdListener(TinyFrame *tf, TF_ID frame_id);
+
+/**
+ * Restarts the timeout of the listener for the given ID.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+bool TF_RenewIdListener(TinyFrame *tf, TF_ID id);
+
+/**
+ * Adds a listener for messages of the given type.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+bool TF_AddTypeListener(TinyFrame *tf, TF_TYPE frame_type, TF_Listener cb);
+
+/**
+ * Removes the listener for the given type.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+bool TF_RemoveTypeListener(TinyFrame *tf, TF_TYPE type_);
+
+/**
+ * Adds a listener for all messages not handled by an ID or type listener.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+bool TF_AddGenericListener(TinyFrame *tf, TF_Listener cb);
+
+/**
+ * Removes a generic listener.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+bool TF_RemoveGenericListener(TinyFrame *tf, TF_Listener cb);
+
+/**
+ * Sends a message.
+ *
+ * # Safety
+ * `tf` must be a valid instance and `msg` must point to a valid message whose `data` points to
+ * `len` readable bytes.
+ */
+bool TF_Send(TinyFrame *tf, TF_Msg *msg);
+
+/**
+ * Sends a message with the given type and data.
+ *
+ * # Safety
+ * `tf` must be a valid instance and `data` must point to `len` readable bytes.
+ */
+bool TF_SendSimple(TinyFrame *tf, TF_TYPE type_, const uint8_t *data, TF_LEN len);
+
+/**
+ * Sends a message and adds an ID listener for the response.
+ *
+ * # Safety
+ * `tf` must be a valid instance and `msg` must point to a valid message whose `data` points to
+ * `len` readable bytes.
+ */
+bool TF_Query(TinyFrame *tf,
+              TF_Msg *msg,
+              TF_Listener listener,
+              TF_Listener_Timeout ftimeout,
+              TF_TICKS timeout);
+
+/**
+ * Sends a message with the given type and data and adds an ID listener for the response.
+ *
+ * # Safety
+ * `tf` must be a valid instance and `data` must point to `len` readable bytes.
+ */
+bool TF_QuerySimple(TinyFrame *tf,
+                    TF_TYPE type_,
+                    const uint8_t *data,
+                    TF_LEN len,
+                    TF_Listener listener,
+                    TF_Listener_Timeout ftimeout,
+                    TF_TICKS timeout);
+
+/**
+ * Sends a response to a received message, reusing its ID.
+ *
+ * # Safety
+ * `tf` must be a valid instance and `msg` must point to a valid message whose `data` points to
+ * `len` readable bytes.
+ */
+bool TF_Respond(TinyFrame *tf, TF_Msg *msg);
+
+/**
+ * Advances timeouts by one tick. Should be called periodically.
+ *
+ * # Safety
+ * `tf` must be a valid instance.
+ */
+void TF_Tick(TinyFrame *tf);
+
+/**
+ * Resets a message to all zeros.
+ *
+ * # Safety
+ * `msg` must point to a writable message.
+ */
+void TF_ClearMsg(TF_Msg *msg);
+
+#endif  /* TINY_FRAME_H */
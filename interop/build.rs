@@ -0,0 +1,268 @@
+//! Compiles a driver program for upstream TinyFrame for every tested frame layout.
+//!
+//! Each layout needs its own `TF_Config.h`, so rather than linking several copies of TinyFrame
+//! into the tests, every layout is built into a separate executable that the tests talk to over
+//! stdin and stdout. The list of executables is written to `helpers.rs`, together with a
+//! `FrameFormat` for every layout so that the generic codec can be tested as well.
+//!
+//! Missing TinyFrame sources are an error, since the tests would not test anything without
+//! them. Setting `TINYFRAME_INTEROP_SKIP` builds without them and marks the tests as ignored.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A tested layout: SOF byte, ID, length and type widths, and the checksum.
+struct Layout {
+    sof: Option<u8>,
+    id_bytes: usize,
+    len_bytes: usize,
+    type_bytes: usize,
+    checksum: Checksum,
+}
+
+#[derive(Clone, Copy)]
+enum Checksum {
+    None,
+    Xor,
+    Crc16,
+    Crc32,
+}
+
+impl Checksum {
+    fn c_name(self) -> &'static str {
+        match self {
+            Checksum::None => "TF_CKSUM_NONE",
+            Checksum::Xor => "TF_CKSUM_XOR",
+            Checksum::Crc16 => "TF_CKSUM_CRC16",
+            Checksum::Crc32 => "TF_CKSUM_CRC32",
+        }
+    }
+
+    fn rust_name(self) -> &'static str {
+        match self {
+            Checksum::None => "None",
+            Checksum::Xor => "Xor",
+            Checksum::Crc16 => "Crc16",
+            Checksum::Crc32 => "Crc32",
+        }
+    }
+
+    fn rust_type(self) -> &'static str {
+        match self {
+            Checksum::None => "NoCheck",
+            Checksum::Xor => "XorSum",
+            Checksum::Crc16 => "Crc16Sum",
+            Checksum::Crc32 => "Crc32Sum",
+        }
+    }
+}
+
+/// Returns the unsigned integer type of a field width.
+fn int_type(bytes: usize) -> &'static str {
+    match bytes {
+        1 => "u8",
+        2 => "u16",
+        4 => "u32",
+        8 => "u64",
+        _ => panic!("no integer type with {} bytes", bytes),
+    }
+}
+
+fn layouts() -> Vec<Layout> {
+    let mut layouts = Vec::new();
+
+    // every checksum with and without SOF byte, using the upstream default widths
+    for &sof in &[Some(0x01), None] {
+        for &checksum in &[
+            Checksum::None,
+            Checksum::Xor,
+            Checksum::Crc16,
+            Checksum::Crc32,
+        ] {
+            layouts.push(Layout {
+                sof,
+                id_bytes: 1,
+                len_bytes: 2,
+                type_bytes: 1,
+                checksum,
+            });
+        }
+    }
+
+    // other field widths
+    let widths = [
+        (1, 1, 1, Checksum::Xor, Some(0x55)),
+        (2, 2, 2, Checksum::Crc16, Some(0x01)),
+        (4, 4, 4, Checksum::Crc32, Some(0x01)),
+        (2, 1, 4, Checksum::Crc16, None),
+        (4, 2, 1, Checksum::Xor, Some(0xaa)),
+        (1, 4, 2, Checksum::None, Some(0x01)),
+    ];
+    for &(id_bytes, len_bytes, type_bytes, checksum, sof) in &widths {
+        layouts.push(Layout {
+            sof,
+            id_bytes,
+            len_bytes,
+            type_bytes,
+            checksum,
+        });
+    }
+
+    layouts
+}
+
+fn config_header(layout: &Layout) -> String {
+    format!(
+        "#ifndef TF_CONFIG_H
+#define TF_CONFIG_H
+
+#include <stdint.h>
+#include <stdio.h>
+
+#define TF_ID_BYTES {id}
+#define TF_LEN_BYTES {len}
+#define TF_TYPE_BYTES {ty}
+#define TF_CKSUM_TYPE {cksum}
+#define TF_USE_SOF_BYTE {use_sof}
+#define TF_SOF_BYTE {sof}
+
+typedef uint16_t TF_TICKS;
+typedef uint8_t TF_COUNT;
+
+#define TF_MAX_PAYLOAD_RX 4096
+#define TF_SENDBUF_LEN 64
+#define TF_MAX_ID_LST 4
+#define TF_MAX_TYPE_LST 4
+#define TF_MAX_GEN_LST 4
+#define TF_PARSER_TIMEOUT_TICKS 10
+#define TF_USE_MUTEX 0
+
+#define TF_Error(format, ...) fprintf(stderr, \"[TF] \" format \"\\n\", ##__VA_ARGS__)
+
+#endif
+",
+        id = layout.id_bytes,
+        len = layout.len_bytes,
+        ty = layout.type_bytes,
+        cksum = layout.checksum.c_name(),
+        use_sof = layout.sof.is_some() as u8,
+        sof = layout.sof.unwrap_or(0),
+    )
+}
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let helper_src = manifest_dir.join("c").join("helper.c");
+    let tf_src = env::var_os("TINYFRAME_SRC")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| manifest_dir.join("vendor").join("TinyFrame"));
+
+    println!("cargo:rerun-if-changed={}", helper_src.display());
+    println!("cargo:rerun-if-changed={}", tf_src.display());
+    println!("cargo:rerun-if-env-changed=TINYFRAME_SRC");
+    println!("cargo:rerun-if-env-changed=TINYFRAME_INTEROP_SKIP");
+    println!("cargo:rustc-check-cfg=cfg(tinyframe_missing)");
+
+    let mut helpers = String::from("pub const HELPERS: &[Helper] = &[\n");
+    let mut formats = String::new();
+
+    let sources_found = ["TinyFrame.c", "TinyFrame.h"]
+        .iter()
+        .all(|file| tf_src.join(file).exists());
+    if !sources_found {
+        if env::var_os("TINYFRAME_INTEROP_SKIP").is_none() {
+            panic!(
+                "upstream TinyFrame sources not found in {}; see interop/vendor/README.md, or \
+                 set TINYFRAME_INTEROP_SKIP=1 to build without them and skip the interop tests",
+                tf_src.display()
+            );
+        }
+        println!("cargo:warning=TINYFRAME_INTEROP_SKIP is set, the interop tests will be ignored");
+        println!("cargo:rustc-cfg=tinyframe_missing");
+    } else {
+        for (i, layout) in layouts().iter().enumerate() {
+            let exe = build_helper(
+                &out_dir.join(format!("layout{}", i)),
+                &tf_src,
+                &helper_src,
+                layout,
+            );
+            writeln!(
+                formats,
+                "pub enum Layout{i} {{}}
+
+impl tiny_frame::FrameFormat for Layout{i} {{
+    type Id = {id};
+    type Len = {len};
+    type Type = {ty};
+    type Checksum = tiny_frame::{cksum};
+    const SOF: Option<u8> = {sof:?};
+}}
+",
+                i = i,
+                id = int_type(layout.id_bytes),
+                len = int_type(layout.len_bytes),
+                ty = int_type(layout.type_bytes),
+                cksum = layout.checksum.rust_type(),
+                sof = layout.sof,
+            )
+            .unwrap();
+            writeln!(
+                helpers,
+                "    Helper {{
+        config: FrameConfig {{
+            sof: {:?},
+            id_bytes: {},
+            len_bytes: {},
+            type_bytes: {},
+            checksum: tiny_frame::dynamic::ChecksumKind::{},
+        }},
+        path: {:?},
+        generic: Generic::<Layout{}>::boxed,
+    }},",
+                layout.sof,
+                layout.id_bytes,
+                layout.len_bytes,
+                layout.type_bytes,
+                layout.checksum.rust_name(),
+                exe,
+                i,
+            )
+            .unwrap();
+        }
+    }
+    helpers.push_str("];\n");
+
+    fs::write(out_dir.join("helpers.rs"), formats + &helpers).unwrap();
+}
+
+fn build_helper(dir: &Path, tf_src: &Path, helper_src: &Path, layout: &Layout) -> PathBuf {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("TF_Config.h"), config_header(layout)).unwrap();
+
+    let exe = dir.join("helper");
+    let compiler = cc::Build::new().opt_level(1).get_compiler();
+    assert!(
+        !compiler.is_like_msvc(),
+        "the interop tests require a gcc-compatible C compiler"
+    );
+
+    let status = compiler
+        .to_command()
+        .arg("-I")
+        .arg(dir)
+        .arg("-I")
+        .arg(tf_src)
+        .arg(helper_src)
+        .arg(tf_src.join("TinyFrame.c"))
+        .arg("-o")
+        .arg(&exe)
+        .status()
+        .expect("failed to run C compiler");
+    assert!(status.success(), "failed to compile TinyFrame helper");
+
+    exe
+}
@@ -0,0 +1,133 @@
+/*
+ * Line-based driver for upstream TinyFrame, used by the interop tests.
+ *
+ * Usage: helper [master|slave]
+ *
+ * Commands, one per line. Numbers are hex, payloads are hex strings or "-" if empty:
+ *   S <type> <data>       send a message with a new ID, prints "F <frame>"
+ *   R <id> <type> <data>  send a response with the given ID, prints "F <frame>"
+ *   A <bytes>             accept bytes, prints "M <id> <type> <data>" for every
+ *                         received message followed by "."
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "TinyFrame.h"
+
+#define MAX_DATA 4096
+
+static uint8_t tx_buf[MAX_DATA * 2];
+static size_t tx_len;
+
+void TF_WriteImpl(TinyFrame *tf, const uint8_t *buff, uint32_t len)
+{
+    (void) tf;
+    if (tx_len + len > sizeof(tx_buf)) {
+        fprintf(stderr, "helper: tx buffer overflow\n");
+        exit(1);
+    }
+    memcpy(tx_buf + tx_len, buff, len);
+    tx_len += len;
+}
+
+static void print_hex(const uint8_t *buf, size_t len)
+{
+    size_t i;
+    if (len == 0) {
+        putchar('-');
+    }
+    for (i = 0; i < len; i++) {
+        printf("%02x", buf[i]);
+    }
+}
+
+static size_t parse_hex(const char *hex, uint8_t *out)
+{
+    size_t len = 0;
+    unsigned int byte;
+    if (hex == NULL || strcmp(hex, "-") == 0) {
+        return 0;
+    }
+    while (hex[0] && hex[1] && len < MAX_DATA) {
+        sscanf(hex, "%2x", &byte);
+        out[len++] = (uint8_t) byte;
+        hex += 2;
+    }
+    return len;
+}
+
+static unsigned long long parse_num(const char *num)
+{
+    return num == NULL ? 0 : strtoull(num, NULL, 16);
+}
+
+static TF_Result on_message(TinyFrame *tf, TF_Msg *msg)
+{
+    (void) tf;
+    printf("M %llx %llx ", (unsigned long long) msg->frame_id, (unsigned long long) msg->type);
+    print_hex(msg->data, msg->len);
+    putchar('\n');
+    return TF_STAY;
+}
+
+static void send_frame(TinyFrame *tf, TF_Msg *msg, bool respond)
+{
+    tx_len = 0;
+    if (!(respond ? TF_Respond(tf, msg) : TF_Send(tf, msg))) {
+        fprintf(stderr, "helper: send failed\n");
+        exit(1);
+    }
+    printf("F ");
+    print_hex(tx_buf, tx_len);
+    putchar('\n');
+}
+
+int main(int argc, char **argv)
+{
+    static char line[MAX_DATA * 4];
+    static uint8_t data[MAX_DATA];
+    TF_Peer peer = argc > 1 && strcmp(argv[1], "master") == 0 ? TF_MASTER : TF_SLAVE;
+    TinyFrame *tf = TF_Init(peer);
+    TF_Msg msg;
+    char *cmd;
+
+    TF_AddGenericListener(tf, on_message);
+
+    while (fgets(line, sizeof(line), stdin)) {
+        line[strcspn(line, "\r\n")] = 0;
+        cmd = strtok(line, " ");
+        if (cmd == NULL) {
+            continue;
+        }
+
+        TF_ClearMsg(&msg);
+        switch (cmd[0]) {
+            case 'S':
+                msg.type = (TF_TYPE) parse_num(strtok(NULL, " "));
+                msg.len = (TF_LEN) parse_hex(strtok(NULL, " "), data);
+                msg.data = data;
+                send_frame(tf, &msg, false);
+                break;
+            case 'R':
+                msg.frame_id = (TF_ID) parse_num(strtok(NULL, " "));
+                msg.type = (TF_TYPE) parse_num(strtok(NULL, " "));
+                msg.len = (TF_LEN) parse_hex(strtok(NULL, " "), data);
+                msg.data = data;
+                send_frame(tf, &msg, true);
+                break;
+            case 'A':
+                TF_Accept(tf, data, (uint32_t) parse_hex(strtok(NULL, " "), data));
+                printf(".\n");
+                break;
+            default:
+                fprintf(stderr, "helper: unknown command %s\n", cmd);
+                return 1;
+        }
+        fflush(stdout);
+    }
+
+    TF_DeInit(tf);
+    return 0;
+}
@@ -0,0 +1,264 @@
+//! Wire compatibility tests against upstream [TinyFrame](https://github.com/MightyPork/TinyFrame).
+//!
+//! The build script compiles upstream TinyFrame for a number of frame layouts (see
+//! `vendor/README.md`). Each build is a small driver program, which is wrapped by [CPeer] so that
+//! tests can send and receive frames with it. The generic codec is tested through [Generic],
+//! which is instantiated with a matching `FrameFormat` for every layout.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use tiny_frame::dynamic::{DynMsg, FrameConfig};
+use tiny_frame::number::{BufferReadable, BufferWritable, FrameType};
+use tiny_frame::{FrameFormat, Msg, MsgDecoder, MsgEncoder};
+
+/// A compiled upstream TinyFrame driver for one frame layout.
+#[derive(Debug)]
+pub struct Helper {
+    /// The frame layout TinyFrame was compiled with.
+    pub config: FrameConfig,
+
+    /// Path to the driver executable.
+    pub path: &'static str,
+
+    /// Creates the generic codec for this layout. The argument is whether it is the master peer.
+    pub generic: fn(bool) -> Box<dyn GenericCodec>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/helpers.rs"));
+
+/// The generic codec of `tiny_frame`, with field values converted to and from `u64`.
+pub trait GenericCodec {
+    /// Encodes a message with [Msg::encode] and returns the frame.
+    fn encode(&mut self, msg: &DynMsg) -> Vec<u8>;
+
+    /// Passes bytes to [MsgDecoder] and returns the messages it decoded. Panics if a frame
+    /// fails to decode.
+    fn decode(&mut self, bytes: &[u8]) -> Vec<DynMsg>;
+}
+
+/// A [GenericCodec] for the frame format `F`.
+pub struct Generic<F: FrameFormat> {
+    encoder: MsgEncoder<F>,
+    decoder: MsgDecoder<F>,
+}
+
+impl<F: FrameFormat + 'static> Generic<F> {
+    /// Creates a boxed codec.
+    pub fn boxed(is_master: bool) -> Box<dyn GenericCodec> {
+        let mut encoder = MsgEncoder::new();
+        encoder.is_master = is_master;
+        Box::new(Generic {
+            encoder,
+            decoder: MsgDecoder::<F>::new(),
+        })
+    }
+}
+
+impl<F: FrameFormat> GenericCodec for Generic<F> {
+    fn encode(&mut self, msg: &DynMsg) -> Vec<u8> {
+        let msg_type = F::Type::try_from_raw(from_u64(msg.msg_type))
+            .unwrap_or_else(|_| panic!("type {:#x} is not valid", msg.msg_type));
+        let msg: Msg<F> = Msg {
+            id: from_u64(msg.id),
+            is_response: msg.is_response,
+            msg_type,
+            data: msg.data.clone(),
+        };
+
+        let mut frame = Vec::new();
+        msg.encode(&mut frame, &mut self.encoder).unwrap();
+        frame
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Vec<DynMsg> {
+        bytes
+            .iter()
+            .filter_map(|&b| match self.decoder.try_accept(b) {
+                Some(Ok(msg)) => Some(DynMsg {
+                    id: to_u64(msg.id),
+                    is_response: false,
+                    msg_type: to_u64(msg.msg_type.to_raw()),
+                    data: msg.data,
+                }),
+                Some(Err(err)) => panic!("failed to decode frame: {}", err),
+                None => None,
+            })
+            .collect()
+    }
+}
+
+/// Converts a `u64` to a field value, dropping bytes that do not fit.
+fn from_u64<T: BufferReadable + Default>(value: u64) -> T {
+    let bytes = value.to_be_bytes();
+    let mut buf = &bytes[8 - T::size()..];
+    T::read_from_buf(&mut buf).unwrap()
+}
+
+/// Converts a field value to a `u64`.
+fn to_u64<T: BufferWritable>(value: T) -> u64 {
+    let mut bytes = Vec::new();
+    value.write_to_buf(&mut bytes).unwrap();
+    bytes.iter().fold(0, |n, &b| n << 8 | b as u64)
+}
+
+/// A running upstream TinyFrame instance.
+pub struct CPeer {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl CPeer {
+    /// Starts a TinyFrame instance.
+    pub fn spawn(helper: &Helper, is_master: bool) -> CPeer {
+        let mut child = Command::new(helper.path)
+            .arg(if is_master { "master" } else { "slave" })
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("failed to start TinyFrame helper");
+
+        CPeer {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+        }
+    }
+
+    /// Sends a message with a new ID and returns the encoded frame.
+    pub fn send(&mut self, msg_type: u64, data: &[u8]) -> Vec<u8> {
+        self.command(&format!("S {:x} {}", msg_type, to_hex(data)));
+        self.read_frame()
+    }
+
+    /// Sends a response with the given ID and returns the encoded frame.
+    pub fn respond(&mut self, id: u64, msg_type: u64, data: &[u8]) -> Vec<u8> {
+        self.command(&format!("R {:x} {:x} {}", id, msg_type, to_hex(data)));
+        self.read_frame()
+    }
+
+    /// Passes received bytes to TinyFrame and returns the messages it decoded.
+    pub fn accept(&mut self, bytes: &[u8]) -> Vec<DynMsg> {
+        self.command(&format!("A {}", to_hex(bytes)));
+
+        let mut msgs = Vec::new();
+        loop {
+            let line = self.read_line();
+            if line == "." {
+                return msgs;
+            }
+
+            let fields: Vec<&str> = line.split(' ').collect();
+            assert_eq!(fields.len(), 4, "unexpected helper output: {}", line);
+            assert_eq!(fields[0], "M", "unexpected helper output: {}", line);
+            msgs.push(DynMsg {
+                id: u64::from_str_radix(fields[1], 16).unwrap(),
+                is_response: false,
+                msg_type: u64::from_str_radix(fields[2], 16).unwrap(),
+                data: from_hex(fields[3]),
+            });
+        }
+    }
+
+    fn command(&mut self, command: &str) {
+        writeln!(self.stdin, "{}", command).expect("TinyFrame helper exited");
+        self.stdin.flush().expect("TinyFrame helper exited");
+    }
+
+    fn read_line(&mut self) -> String {
+        let mut line = String::new();
+        self.stdout.read_line(&mut line).unwrap();
+        assert!(!line.is_empty(), "TinyFrame helper exited");
+        line.trim_end().to_string()
+    }
+
+    fn read_frame(&mut self) -> Vec<u8> {
+        let line = self.read_line();
+        match line.strip_prefix("F ") {
+            Some(hex) => from_hex(hex),
+            None => panic!("unexpected helper output: {}", line),
+        }
+    }
+}
+
+impl Drop for CPeer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "-".to_string();
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    if hex == "-" {
+        return Vec::new();
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// A small deterministic random number generator (xorshift64*), so that failures can be
+/// reproduced.
+pub struct Rng(u64);
+
+impl Rng {
+    /// Creates a generator with the given seed.
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed | 1)
+    }
+
+    /// Returns a random number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a random number below `max`, or any number if `max` is 0.
+    pub fn below(&mut self, max: u64) -> u64 {
+        if max == 0 {
+            self.next_u64()
+        } else {
+            self.next_u64() % max
+        }
+    }
+
+    /// Returns random bytes.
+    pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+}
+
+/// Returns a mask with the given number of low bytes set.
+pub fn field_mask(bytes: usize) -> u64 {
+    if bytes >= 8 {
+        u64::MAX
+    } else {
+        (1 << (bytes * 8)) - 1
+    }
+}
+
+/// Returns a random message for the given layout.
+pub fn random_msg(rng: &mut Rng, config: &FrameConfig) -> DynMsg {
+    let max_len = config.max_len().min(300);
+    let len = match rng.below(8) {
+        0 => 0,
+        _ => rng.below(max_len + 1) as usize,
+    };
+
+    DynMsg {
+        id: rng.next_u64() & field_mask(config.id_bytes),
+        is_response: true,
+        msg_type: rng.next_u64() & field_mask(config.type_bytes),
+        data: rng.bytes(len),
+    }
+}
@@ -0,0 +1,210 @@
+use tiny_frame::dynamic::{ChecksumKind, DynMsg, DynMsgDecoder, DynMsgEncoder, FrameConfig};
+use tiny_frame::{DefaultFormat, Msg, MsgDecoder, MsgEncoder};
+use tiny_frame_interop::{random_msg, CPeer, Rng, HELPERS};
+
+const MESSAGES: usize = 200;
+
+/// The layout of [DefaultFormat].
+const DEFAULT_CONFIG: FrameConfig = FrameConfig {
+    sof: Some(0x01),
+    id_bytes: 1,
+    len_bytes: 2,
+    type_bytes: 1,
+    checksum: ChecksumKind::Crc16,
+};
+
+fn decode_all(decoder: &mut DynMsgDecoder, bytes: &[u8]) -> Vec<DynMsg> {
+    bytes
+        .iter()
+        .filter_map(|&b| match decoder.try_accept(b) {
+            Some(Ok(msg)) => Some(msg),
+            Some(Err(err)) => panic!("failed to decode frame from C: {}", err),
+            None => None,
+        })
+        .collect()
+}
+
+fn received(msg: &DynMsg) -> DynMsg {
+    DynMsg {
+        is_response: false,
+        ..msg.clone()
+    }
+}
+
+#[test]
+#[cfg_attr(tinyframe_missing, ignore = "TINYFRAME_INTEROP_SKIP is set")]
+fn rust_to_c() {
+    assert!(!HELPERS.is_empty());
+
+    for (i, helper) in HELPERS.iter().enumerate() {
+        let mut rng = Rng::new(i as u64);
+        let mut c = CPeer::spawn(helper, false);
+        let mut encoder = DynMsgEncoder::new(helper.config);
+        encoder.is_master = true;
+
+        let mut batch = Vec::new();
+        let mut expected = Vec::new();
+
+        for _ in 0..MESSAGES {
+            let mut msg = random_msg(&mut rng, &helper.config);
+            msg.is_response = rng.below(2) == 0;
+
+            let mut frame = Vec::new();
+            msg.clone().encode(&mut frame, &mut encoder).unwrap();
+
+            // messages that are not responses get their ID from the encoder
+            let mut decoded = DynMsgDecoder::new(helper.config);
+            let sent = decode_all(&mut decoded, &frame).remove(0);
+            assert_eq!(
+                c.accept(&frame),
+                std::slice::from_ref(&sent),
+                "{:?}: {:?}",
+                helper.config,
+                msg
+            );
+
+            batch.extend_from_slice(&frame);
+            expected.push(sent);
+
+            if batch.len() > 1000 {
+                assert_eq!(c.accept(&batch), expected, "{:?}", helper.config);
+                batch.clear();
+                expected.clear();
+            }
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(tinyframe_missing, ignore = "TINYFRAME_INTEROP_SKIP is set")]
+fn c_to_rust() {
+    assert!(!HELPERS.is_empty());
+
+    for (i, helper) in HELPERS.iter().enumerate() {
+        let mut rng = Rng::new(!(i as u64));
+        let mut c = CPeer::spawn(helper, true);
+        let mut decoder = DynMsgDecoder::new(helper.config);
+        let mut encoder = DynMsgEncoder::new(helper.config);
+        encoder.is_master = true;
+
+        for _ in 0..MESSAGES {
+            let msg = random_msg(&mut rng, &helper.config);
+
+            let is_response = rng.below(2) == 0;
+            let frame = if is_response {
+                c.respond(msg.id, msg.msg_type, &msg.data)
+            } else {
+                let frame = c.send(msg.msg_type, &msg.data);
+
+                // both sides allocate the same IDs
+                let mut expected = Vec::new();
+                DynMsg {
+                    is_response: false,
+                    ..msg.clone()
+                }
+                .encode(&mut expected, &mut encoder)
+                .unwrap();
+                assert_eq!(frame, expected, "{:?}: {:?}", helper.config, msg);
+
+                frame
+            };
+
+            let msgs = decode_all(&mut decoder, &frame);
+            assert_eq!(msgs.len(), 1, "{:?}: {:?}", helper.config, msg);
+            if is_response {
+                assert_eq!(msgs[0].id, msg.id);
+            }
+            assert_eq!(msgs[0].msg_type, msg.msg_type);
+            assert_eq!(msgs[0].data, msg.data);
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(tinyframe_missing, ignore = "TINYFRAME_INTEROP_SKIP is set")]
+fn default_format() {
+    let helper = HELPERS
+        .iter()
+        .find(|helper| helper.config == DEFAULT_CONFIG)
+        .expect("the layout of DefaultFormat is not tested");
+
+    let mut rng = Rng::new(42);
+    let mut c = CPeer::spawn(helper, false);
+    let mut encoder = MsgEncoder::<DefaultFormat>::new();
+    let mut decoder = MsgDecoder::<DefaultFormat>::new();
+
+    for _ in 0..MESSAGES {
+        let msg = random_msg(&mut rng, &helper.config);
+
+        let mut frame = Vec::new();
+        let id = Msg::<DefaultFormat> {
+            id: 0,
+            is_response: false,
+            msg_type: msg.msg_type as u8,
+            data: msg.data.clone(),
+        }
+        .encode(&mut frame, &mut encoder)
+        .unwrap();
+
+        let expected = DynMsg {
+            id: id as u64,
+            ..received(&msg)
+        };
+        assert_eq!(c.accept(&frame), [expected]);
+
+        let frame = c.respond(msg.id, msg.msg_type, &msg.data);
+        let decoded = frame.iter().find_map(|&b| decoder.accept(b)).unwrap();
+        assert_eq!(decoded.id as u64, msg.id);
+        assert_eq!(decoded.msg_type as u64, msg.msg_type);
+        assert_eq!(decoded.data, msg.data);
+    }
+}
+
+#[test]
+#[cfg_attr(tinyframe_missing, ignore = "TINYFRAME_INTEROP_SKIP is set")]
+fn generic_codec() {
+    assert!(!HELPERS.is_empty());
+
+    for (i, helper) in HELPERS.iter().enumerate() {
+        let mut rng = Rng::new(0x6e6e + i as u64);
+        let mut generic = (helper.generic)(true);
+        let mut dynamic = DynMsgEncoder::new(helper.config);
+        dynamic.is_master = true;
+        let mut c = CPeer::spawn(helper, false);
+
+        // generic encoder to C
+        for _ in 0..MESSAGES {
+            let mut msg = random_msg(&mut rng, &helper.config);
+            msg.is_response = rng.below(2) == 0;
+
+            let frame = generic.encode(&msg);
+
+            // the generic and the runtime codec produce the same frames
+            let mut expected = Vec::new();
+            msg.clone().encode(&mut expected, &mut dynamic).unwrap();
+            assert_eq!(frame, expected, "{:?}: {:?}", helper.config, msg);
+
+            let sent = decode_all(&mut DynMsgDecoder::new(helper.config), &frame);
+            assert_eq!(c.accept(&frame), sent, "{:?}: {:?}", helper.config, msg);
+        }
+
+        // C to generic decoder
+        for _ in 0..MESSAGES {
+            let msg = random_msg(&mut rng, &helper.config);
+            let frame = if rng.below(2) == 0 {
+                c.respond(msg.id, msg.msg_type, &msg.data)
+            } else {
+                c.send(msg.msg_type, &msg.data)
+            };
+
+            let expected = decode_all(&mut DynMsgDecoder::new(helper.config), &frame);
+            assert_eq!(
+                generic.decode(&frame),
+                expected,
+                "{:?}: {:?}",
+                helper.config,
+                msg
+            );
+        }
+    }
+}
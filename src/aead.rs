@@ -0,0 +1,288 @@
+//! Encrypted messages.
+//!
+//! This module is only available with the `aead` feature. [AeadEncoder] encrypts the payload of
+//! every message with ChaCha20-Poly1305 using a pre-shared 256-bit key, and [AeadDecoder]
+//! decrypts it and rejects messages that were tampered with or replayed. The raw ID and type
+//! fields are authenticated as associated data, so they cannot be changed either.
+//!
+//! The encrypted payload is a big endian `u32` message counter, followed by the ciphertext and
+//! the 16 byte tag ([OVERHEAD] bytes in total). The 12 byte nonce is built from the role of the
+//! sender (master or slave, see [MsgEncoder::is_master]), the counter and the message ID, so the
+//! two peers never produce the same nonce from the same counter. The counter is not secret, but
+//! must increase with every message, which also provides replay protection.
+//!
+//! Counters start at 0. A sender that restarts with the same key sends the same nonces again,
+//! which reveals the XOR of the plaintexts and allows forging tags, and a receiver that restarts
+//! accepts previously recorded frames again. Either change the key on every restart, or persist
+//! the counters: store [AeadEncoder::counter] before sending messages with higher counters (for
+//! example by reserving a block of counters at a time), and [AeadDecoder::counter] after
+//! receiving, and restore them with `set_counter` on startup.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use tiny_frame::aead::{AeadDecoder, AeadEncoder, AeadError};
+//!
+//! let key = [0x42; 32];
+//!
+//! let mut master = MsgEncoder::new();
+//! master.is_master = true;
+//! let mut encoder = AeadEncoder::<DefaultFormat>::new(master, &key);
+//! let mut decoder = AeadDecoder::<DefaultFormat>::new(MsgDecoder::new(), &key, false);
+//!
+//! let msg = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 0x50,
+//!     data: b"temperature: 21.5".to_vec(),
+//! };
+//!
+//! let mut frame = Vec::new();
+//! encoder.encode(msg, &mut frame).unwrap();
+//! assert!(!frame.windows(11).any(|w| w == b"temperature"));
+//!
+//! let received = frame.iter().find_map(|&b| decoder.accept(b)).unwrap();
+//! assert_eq!(received.unwrap().data, b"temperature: 21.5");
+//!
+//! // replaying the frame is rejected
+//! let replayed = frame.iter().find_map(|&b| decoder.accept(b)).unwrap();
+//! assert_eq!(replayed, Err(AeadError::Replayed));
+//!
+//! // after a restart, the receiver still rejects the frame once its counter is restored
+//! let saved = decoder.counter();
+//! let mut decoder = AeadDecoder::<DefaultFormat>::new(MsgDecoder::new(), &key, false);
+//! decoder.set_counter(saved);
+//! let replayed = frame.iter().find_map(|&b| decoder.accept(b)).unwrap();
+//! assert_eq!(replayed, Err(AeadError::Replayed));
+//! ```
+
+use crate::number::{BufferReadable, BufferWritable, FrameType};
+use crate::{FrameFormat, Msg, MsgDecoder, MsgEncoder};
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
+use std::fmt;
+use std::io::{self, Write};
+
+/// Number of bytes added to the payload by encryption.
+pub const OVERHEAD: usize = 4 + 16;
+
+/// Errors returned when decrypting a received message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AeadError {
+    /// The payload is shorter than the counter and tag.
+    Truncated,
+
+    /// The message could not be decrypted, because it was tampered with or encrypted with a
+    /// different key.
+    Decrypt,
+
+    /// The counter is not greater than that of the previous message.
+    Replayed,
+}
+
+impl fmt::Display for AeadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AeadError::Truncated => write!(f, "encrypted payload is truncated"),
+            AeadError::Decrypt => write!(f, "message decryption failed"),
+            AeadError::Replayed => write!(f, "message counter was replayed"),
+        }
+    }
+}
+
+impl std::error::Error for AeadError {}
+
+/// A message encoder that encrypts messages.
+pub struct AeadEncoder<F: FrameFormat> {
+    encoder: MsgEncoder<F>,
+    cipher: ChaCha20Poly1305,
+    counter: u32,
+}
+
+impl<F: FrameFormat> AeadEncoder<F> {
+    /// Creates a new encoder with the given pre-shared key.
+    pub fn new(encoder: MsgEncoder<F>, key: &[u8; 32]) -> AeadEncoder<F> {
+        AeadEncoder {
+            encoder,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+            counter: 0,
+        }
+    }
+
+    /// Replaces the key and restarts the message counter.
+    pub fn set_key(&mut self, key: &[u8; 32]) {
+        self.cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+        self.counter = 0;
+    }
+
+    /// Returns the counter of the last sent message, or 0 if no message has been sent.
+    pub fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    /// Sets the counter of the last sent message, such as one saved before a restart. The next
+    /// message is sent with a higher counter. Counters must never be reused with the same key.
+    pub fn set_counter(&mut self, counter: u32) {
+        self.counter = counter;
+    }
+
+    /// Returns the inner encoder.
+    pub fn encoder(&mut self) -> &mut MsgEncoder<F> {
+        &mut self.encoder
+    }
+
+    /// Encrypts a message and encodes it into the given output. If the message is not a
+    /// response, a new ID will be assigned. Returns the ID of the encoded message.
+    ///
+    /// Fails once the counter is exhausted, after 2^32 - 1 messages; the key must be changed
+    /// before then.
+    pub fn encode<W: Write>(&mut self, mut msg: Msg<F>, out: &mut W) -> io::Result<F::Id> {
+        if self.counter == u32::MAX {
+            return Err(io::Error::other("message counter exhausted"));
+        }
+        self.counter += 1;
+
+        if !msg.is_response {
+            msg.id = self.encoder.next_id();
+            msg.is_response = true;
+        }
+
+        let header = header(&msg);
+        let nonce = nonce::<F>(self.encoder.is_master, self.counter, &header);
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(&nonce, &header, &mut msg.data)
+            .map_err(|_| io::Error::other("encryption failed"))?;
+
+        let mut data = Vec::with_capacity(msg.data.len() + OVERHEAD);
+        data.extend_from_slice(&self.counter.to_be_bytes());
+        data.extend_from_slice(&msg.data);
+        data.extend_from_slice(&tag);
+        msg.data = data;
+
+        msg.encode(out, &mut self.encoder)
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for AeadEncoder<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AeadEncoder")
+            .field("encoder", &self.encoder)
+            .field("counter", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message decoder that decrypts messages.
+pub struct AeadDecoder<F: FrameFormat> {
+    decoder: MsgDecoder<F>,
+    cipher: ChaCha20Poly1305,
+    is_master: bool,
+    counter: u32,
+}
+
+impl<F: FrameFormat> AeadDecoder<F> {
+    /// Creates a new decoder with the given pre-shared key. `is_master` is the role of the
+    /// receiving peer; messages are expected to come from the other role.
+    pub fn new(decoder: MsgDecoder<F>, key: &[u8; 32], is_master: bool) -> AeadDecoder<F> {
+        AeadDecoder {
+            decoder,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+            is_master,
+            counter: 0,
+        }
+    }
+
+    /// Replaces the key and restarts the message counter.
+    pub fn set_key(&mut self, key: &[u8; 32]) {
+        self.cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+        self.counter = 0;
+    }
+
+    /// Returns the counter of the last accepted message, or 0 if no message has been accepted.
+    pub fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    /// Sets the counter of the last accepted message, such as one saved before a restart. Only
+    /// messages with a higher counter are accepted.
+    pub fn set_counter(&mut self, counter: u32) {
+        self.counter = counter;
+    }
+
+    /// Returns the inner decoder.
+    pub fn decoder(&mut self) -> &mut MsgDecoder<F> {
+        &mut self.decoder
+    }
+
+    /// Accepts a byte. Returns the decrypted message once a frame is complete, or an error if
+    /// the frame could not be decrypted. Frames that fail to decode are dropped, as with
+    /// [MsgDecoder::accept].
+    pub fn accept(&mut self, byte: u8) -> Option<Result<Msg<F>, AeadError>> {
+        self.decoder.accept(byte).map(|msg| self.decrypt(msg))
+    }
+
+    /// Decrypts a received message.
+    pub fn decrypt(&mut self, mut msg: Msg<F>) -> Result<Msg<F>, AeadError> {
+        if msg.data.len() < OVERHEAD {
+            return Err(AeadError::Truncated);
+        }
+
+        let mut counter = [0; 4];
+        counter.copy_from_slice(&msg.data[..4]);
+        let counter = u32::from_be_bytes(counter);
+        let tag = *Tag::from_slice(&msg.data[msg.data.len() - 16..]);
+        let mut data = msg.data[4..msg.data.len() - 16].to_vec();
+
+        let header = header(&msg);
+        let nonce = nonce::<F>(!self.is_master, counter, &header);
+        self.cipher
+            .decrypt_in_place_detached(&nonce, &header, &mut data, &tag)
+            .map_err(|_| AeadError::Decrypt)?;
+
+        if counter <= self.counter {
+            return Err(AeadError::Replayed);
+        }
+        self.counter = counter;
+
+        msg.data = data;
+        Ok(msg)
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for AeadDecoder<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AeadDecoder")
+            .field("is_master", &self.is_master)
+            .field("counter", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the raw ID and type fields, which are used as associated data.
+fn header<F: FrameFormat>(msg: &Msg<F>) -> Vec<u8> {
+    let mut header = Vec::new();
+    msg.id
+        .write_to_buf(&mut header)
+        .expect("writing to a Vec cannot fail");
+    msg.msg_type
+        .to_raw()
+        .write_to_buf(&mut header)
+        .expect("writing to a Vec cannot fail");
+    header
+}
+
+/// Builds a nonce from the sender role, the counter and the low 7 bytes of the message ID.
+///
+/// The sender role and counter make the nonce unique as long as counters are not reused with the
+/// same key (see the module documentation); the ID binds it to the frame.
+fn nonce<F: FrameFormat>(sender_is_master: bool, counter: u32, header: &[u8]) -> Nonce {
+    let id = &header[..F::Id::size()];
+    let id = &id[id.len().saturating_sub(7)..];
+
+    let mut nonce = Nonce::default();
+    nonce[0] = sender_is_master as u8;
+    nonce[1..5].copy_from_slice(&counter.to_be_bytes());
+    nonce[12 - id.len()..].copy_from_slice(id);
+    nonce
+}
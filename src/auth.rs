@@ -0,0 +1,332 @@
+//! Authenticated messages.
+//!
+//! Checksums only detect transmission errors; anyone on the bus can produce frames with a valid
+//! checksum. [AuthEncoder] appends a message counter and a SipHash-2-4 tag keyed with a
+//! pre-shared 128-bit key to the payload of every message, and [AuthDecoder] rejects messages
+//! with a wrong tag or a counter that is not greater than the previous one.
+//!
+//! The authenticated payload is the original data followed by a [TRAILER_SIZE] byte trailer:
+//! the big endian `u32` counter and the 8 byte tag. The tag covers the role of the sender
+//! (master or slave, see [MsgEncoder::is_master]), the raw ID and type fields, the counter and
+//! the data, so a frame reflected back to its sender does not verify. The frame checksum is still
+//! used, so this works alongside any [Checksum](crate::Checksum), or with
+//! [NoCheck](crate::NoCheck) in place of one.
+//!
+//! Counters start at 0, so replay protection does not survive a restart on its own: a receiver
+//! that restarts accepts previously recorded frames again, and one that keeps running rejects
+//! the messages of a restarted sender as replayed. Persist the counters, storing
+//! [AuthEncoder::counter] before sending messages with higher counters and [AuthDecoder::counter]
+//! after receiving, and restore them with `set_counter` on startup, or change the key.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use tiny_frame::auth::{AuthDecoder, AuthEncoder, AuthError};
+//!
+//! let key = *b"sixteen byte key";
+//! let mut master = MsgEncoder::new();
+//! master.is_master = true;
+//! let mut encoder = AuthEncoder::<DefaultFormat>::new(master, key);
+//! let mut decoder = AuthDecoder::<DefaultFormat>::new(MsgDecoder::new(), key, false);
+//!
+//! let msg = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 0x40,
+//!     data: b"unlock".to_vec(),
+//! };
+//!
+//! let mut frame = Vec::new();
+//! encoder.encode(msg, &mut frame).unwrap();
+//!
+//! let received = frame.iter().find_map(|&b| decoder.accept(b)).unwrap();
+//! assert_eq!(received.unwrap().data, b"unlock");
+//!
+//! // tampering with the payload is detected even if the frame checksum is fixed up
+//! let mut forged = MsgDecoder::<DefaultFormat>::new();
+//! let mut forged = frame.iter().find_map(|&b| forged.accept(b)).unwrap();
+//! forged.data[0] = b'U';
+//! assert_eq!(decoder.verify(forged), Err(AuthError::BadTag));
+//!
+//! // replaying the same frame is rejected
+//! let replayed = frame.iter().find_map(|&b| decoder.accept(b)).unwrap();
+//! assert_eq!(replayed, Err(AuthError::Replayed));
+//!
+//! // also after a restart, once the saved counter is restored
+//! let saved = decoder.counter();
+//! let mut decoder = AuthDecoder::<DefaultFormat>::new(MsgDecoder::new(), key, false);
+//! decoder.set_counter(saved);
+//! let replayed = frame.iter().find_map(|&b| decoder.accept(b)).unwrap();
+//! assert_eq!(replayed, Err(AuthError::Replayed));
+//!
+//! // the master does not accept its own frames reflected back to it
+//! let mut reflected = AuthDecoder::<DefaultFormat>::new(MsgDecoder::new(), key, true);
+//! let received = frame.iter().find_map(|&b| reflected.accept(b)).unwrap();
+//! assert_eq!(received, Err(AuthError::BadTag));
+//! ```
+
+use crate::number::{BufferWritable, FrameType};
+use crate::{FrameFormat, Msg, MsgDecoder, MsgEncoder};
+use std::fmt;
+use std::io::{self, Write};
+
+/// Size of the authentication trailer appended to payloads.
+pub const TRAILER_SIZE: usize = 4 + 8;
+
+/// Errors returned when verifying a received message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuthError {
+    /// The payload is shorter than the authentication trailer.
+    Truncated,
+
+    /// The tag does not match.
+    BadTag,
+
+    /// The counter is not greater than that of the previous message.
+    Replayed,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthError::Truncated => write!(f, "message has no authentication trailer"),
+            AuthError::BadTag => write!(f, "message authentication failed"),
+            AuthError::Replayed => write!(f, "message counter was replayed"),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// A message encoder that authenticates messages.
+pub struct AuthEncoder<F: FrameFormat> {
+    encoder: MsgEncoder<F>,
+    key: [u8; 16],
+    counter: u32,
+}
+
+impl<F: FrameFormat> AuthEncoder<F> {
+    /// Creates a new encoder with the given key.
+    pub fn new(encoder: MsgEncoder<F>, key: [u8; 16]) -> AuthEncoder<F> {
+        AuthEncoder {
+            encoder,
+            key,
+            counter: 0,
+        }
+    }
+
+    /// Returns the counter of the last sent message, or 0 if no message has been sent.
+    pub fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    /// Sets the counter of the last sent message, such as one saved before a restart. The next
+    /// message is sent with a higher counter.
+    pub fn set_counter(&mut self, counter: u32) {
+        self.counter = counter;
+    }
+
+    /// Returns the inner encoder.
+    pub fn encoder(&mut self) -> &mut MsgEncoder<F> {
+        &mut self.encoder
+    }
+
+    /// Authenticates a message and encodes it into the given output. If the message is not a
+    /// response, a new ID will be assigned. Returns the ID of the encoded message.
+    ///
+    /// Fails once the counter is exhausted, after 2^32 - 1 messages; the key must be changed
+    /// before then.
+    pub fn encode<W: Write>(&mut self, mut msg: Msg<F>, out: &mut W) -> io::Result<F::Id> {
+        if self.counter == u32::MAX {
+            return Err(io::Error::other("message counter exhausted"));
+        }
+        self.counter += 1;
+
+        if !msg.is_response {
+            msg.id = self.encoder.next_id();
+            msg.is_response = true;
+        }
+
+        let tag = tag(&self.key, self.encoder.is_master, &msg, self.counter);
+        msg.data.extend_from_slice(&self.counter.to_be_bytes());
+        msg.data.extend_from_slice(&tag.to_be_bytes());
+        msg.encode(out, &mut self.encoder)
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for AuthEncoder<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AuthEncoder")
+            .field("encoder", &self.encoder)
+            .field("counter", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message decoder that verifies authenticated messages.
+pub struct AuthDecoder<F: FrameFormat> {
+    decoder: MsgDecoder<F>,
+    key: [u8; 16],
+    is_master: bool,
+    counter: u32,
+}
+
+impl<F: FrameFormat> AuthDecoder<F> {
+    /// Creates a new decoder with the given key. `is_master` is the role of the receiving peer;
+    /// messages are expected to come from the other role.
+    pub fn new(decoder: MsgDecoder<F>, key: [u8; 16], is_master: bool) -> AuthDecoder<F> {
+        AuthDecoder {
+            decoder,
+            key,
+            is_master,
+            counter: 0,
+        }
+    }
+
+    /// Returns the counter of the last accepted message, or 0 if no message has been accepted.
+    pub fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    /// Sets the counter of the last accepted message, such as one saved before a restart. Only
+    /// messages with a higher counter are accepted.
+    pub fn set_counter(&mut self, counter: u32) {
+        self.counter = counter;
+    }
+
+    /// Returns the inner decoder.
+    pub fn decoder(&mut self) -> &mut MsgDecoder<F> {
+        &mut self.decoder
+    }
+
+    /// Accepts a byte. Returns the verified message once a frame is complete, or an error if
+    /// the frame could not be verified. Frames that fail to decode are dropped, as with
+    /// [MsgDecoder::accept].
+    pub fn accept(&mut self, byte: u8) -> Option<Result<Msg<F>, AuthError>> {
+        self.decoder.accept(byte).map(|msg| self.verify(msg))
+    }
+
+    /// Verifies a received message and removes the authentication trailer.
+    pub fn verify(&mut self, mut msg: Msg<F>) -> Result<Msg<F>, AuthError> {
+        if msg.data.len() < TRAILER_SIZE {
+            return Err(AuthError::Truncated);
+        }
+
+        let trailer = msg.data.split_off(msg.data.len() - TRAILER_SIZE);
+        let mut counter = [0; 4];
+        counter.copy_from_slice(&trailer[..4]);
+        let counter = u32::from_be_bytes(counter);
+        let mut received_tag = [0; 8];
+        received_tag.copy_from_slice(&trailer[4..]);
+
+        let expected_tag = tag(&self.key, !self.is_master, &msg, counter).to_be_bytes();
+        let diff = expected_tag
+            .iter()
+            .zip(&received_tag)
+            .fold(0, |diff, (a, b)| diff | (a ^ b));
+        if diff != 0 {
+            return Err(AuthError::BadTag);
+        }
+
+        if counter <= self.counter {
+            return Err(AuthError::Replayed);
+        }
+        self.counter = counter;
+
+        Ok(msg)
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for AuthDecoder<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AuthDecoder")
+            .field("is_master", &self.is_master)
+            .field("counter", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Calculates the tag of a message without trailer.
+fn tag<F: FrameFormat>(key: &[u8; 16], sender_is_master: bool, msg: &Msg<F>, counter: u32) -> u64 {
+    let mut input = Vec::with_capacity(msg.data.len() + 32);
+    input.push(sender_is_master as u8);
+    msg.id
+        .write_to_buf(&mut input)
+        .expect("writing to a Vec cannot fail");
+    msg.msg_type
+        .to_raw()
+        .write_to_buf(&mut input)
+        .expect("writing to a Vec cannot fail");
+    input.extend_from_slice(&counter.to_be_bytes());
+    input.extend_from_slice(&msg.data);
+
+    siphash24(key, &input)
+}
+
+/// Calculates the SipHash-2-4 of `data`, as used for the tag.
+///
+/// # Examples
+/// ```
+/// # use tiny_frame::auth::siphash24;
+/// // test vectors from the SipHash paper, with the key 00 01 .. 0f
+/// let key = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+/// assert_eq!(siphash24(&key, b""), 0x726f_db47_dd0e_0e31);
+/// assert_eq!(siphash24(&key, &[0, 1, 2, 3, 4, 5, 6, 7]), 0x93f5_f579_9a93_2462);
+/// let message: Vec<u8> = (0..15).collect();
+/// assert_eq!(siphash24(&key, &message), 0xa129_ca61_49be_45e5);
+/// ```
+pub fn siphash24(key: &[u8; 16], data: &[u8]) -> u64 {
+    let mut k0 = [0; 8];
+    let mut k1 = [0; 8];
+    k0.copy_from_slice(&key[..8]);
+    k1.copy_from_slice(&key[8..]);
+    let k0 = u64::from_le_bytes(k0);
+    let k1 = u64::from_le_bytes(k1);
+
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut word = [0; 8];
+        word.copy_from_slice(chunk);
+        let m = u64::from_le_bytes(word);
+        v[3] ^= m;
+        round(&mut v);
+        round(&mut v);
+        v[0] ^= m;
+    }
+
+    let mut last = [0; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = data.len() as u8;
+    let m = u64::from_le_bytes(last);
+    v[3] ^= m;
+    round(&mut v);
+    round(&mut v);
+    v[0] ^= m;
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
@@ -0,0 +1,65 @@
+//! Command line handling shared by the binaries.
+
+use std::convert::TryFrom;
+use tiny_frame::dynamic::{ChecksumKind, FrameConfig};
+
+/// The usage text of the frame layout options.
+macro_rules! layout_usage {
+    () => {
+        "      --sof <BYTE|none>    start-of-frame byte (default: 0x01)
+      --id-bytes <N>       width of the ID field (default: 1)
+      --len-bytes <N>      width of the length field (default: 2)
+      --type-bytes <N>     width of the type field (default: 1)
+      --checksum <KIND>    none, xor, crc16 or crc32 (default: crc16)
+"
+    };
+}
+
+/// Returns the layout of [DefaultFormat](tiny_frame::DefaultFormat).
+pub fn default_layout() -> FrameConfig {
+    FrameConfig {
+        sof: Some(0x01),
+        id_bytes: 1,
+        len_bytes: 2,
+        type_bytes: 1,
+        checksum: ChecksumKind::Crc16,
+    }
+}
+
+/// Applies a frame layout option to `layout`, taking its value from `value`. Returns false if
+/// `arg` is not a layout option.
+pub fn parse_layout_arg<V>(
+    layout: &mut FrameConfig,
+    arg: &str,
+    mut value: V,
+) -> Result<bool, String>
+where
+    V: FnMut() -> Result<String, String>,
+{
+    match arg {
+        "--sof" => {
+            layout.sof = match value()?.as_str() {
+                "none" => None,
+                byte => Some(parse_number(byte)?),
+            }
+        }
+        "--id-bytes" => layout.id_bytes = parse_number(&value()?)?,
+        "--len-bytes" => layout.len_bytes = parse_number(&value()?)?,
+        "--type-bytes" => layout.type_bytes = parse_number(&value()?)?,
+        "--checksum" => layout.checksum = value()?.parse().map_err(|err| format!("{}", err))?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal number.
+pub fn parse_number<T: TryFrom<u64>>(s: &str) -> Result<T, String> {
+    let value = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    value
+        .ok()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| format!("invalid number: {}", s))
+}
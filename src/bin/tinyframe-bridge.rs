@@ -0,0 +1,366 @@
+//! Relays a TinyFrame link between a serial device and TCP clients.
+//!
+//! Everything received from the device is sent to all connected clients, and everything a client
+//! sends is written to the device. With `--validate`, both directions are run through a
+//! [DynMsgDecoder] and only frames with valid checksums are forwarded; corrupted frames and
+//! bytes outside of frames are dropped. Statistics for each direction are printed when the
+//! device is closed, and periodically with `--stats`.
+
+#[macro_use]
+mod common;
+
+use common::{default_layout, parse_layout_arg, parse_number};
+use std::env;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tiny_frame::dynamic::{DynMsgDecoder, DynMsgEncoder, FrameConfig};
+use tiny_frame::serial::{Parity, SerialConfig};
+
+const USAGE: &str = concat!(
+    "\
+Usage: tinyframe-bridge [OPTIONS] <DEVICE> <LISTEN_ADDR>
+
+Relays a TinyFrame link between a serial device and TCP clients.
+
+Options:
+  -b, --baud <RATE>        baud rate (default: 115200)
+  -p, --parity <PARITY>    none, odd or even (default: none)
+      --validate           forward only frames with valid checksums
+",
+    layout_usage!(),
+    "      --stats <SECONDS>    print statistics periodically
+  -h, --help               print this help
+
+The frame layout options are only used with --validate."
+);
+
+const READ_BUFFER_SIZE: usize = 1024;
+
+/// Clients that do not accept data within this time are disconnected.
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+struct Options {
+    device: String,
+    listen: String,
+    serial: SerialConfig,
+    /// The frame layout, if frames are validated.
+    validate: Option<FrameConfig>,
+    stats_interval: Option<Duration>,
+}
+
+/// Parses the command line. Returns `None` if help was requested.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, String> {
+    let mut serial = SerialConfig::default();
+    let mut validate = false;
+    let mut layout = default_layout();
+    let mut stats_interval = None;
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-b" | "--baud" => serial.baud_rate = parse_number(&value()?)?,
+            "-p" | "--parity" => {
+                serial.parity = match value()?.as_str() {
+                    "none" => Parity::None,
+                    "odd" => Parity::Odd,
+                    "even" => Parity::Even,
+                    other => return Err(format!("invalid parity: {}", other)),
+                }
+            }
+            "--validate" => validate = true,
+            "--stats" => stats_interval = Some(Duration::from_secs(parse_number(&value()?)?)),
+            arg if parse_layout_arg(&mut layout, arg, &mut value)? => {}
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ => positional.push(arg),
+        }
+    }
+
+    if !layout.is_valid() {
+        return Err("invalid frame layout".into());
+    }
+
+    let mut positional = positional.into_iter();
+    match (positional.next(), positional.next(), positional.next()) {
+        (Some(device), Some(listen), None) => Ok(Some(Options {
+            device,
+            listen,
+            serial,
+            validate: validate.then_some(layout),
+            stats_interval,
+        })),
+        _ => Err("expected a device and a listen address".into()),
+    }
+}
+
+/// Traffic counters for one direction.
+#[derive(Default)]
+struct Stats {
+    bytes: AtomicU64,
+    frames: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes, {} frames, {} dropped",
+            self.bytes.load(Ordering::Relaxed),
+            self.frames.load(Ordering::Relaxed),
+            self.dropped.load(Ordering::Relaxed)
+        )
+    }
+}
+
+/// Relays the bytes of one direction, optionally keeping only valid frames.
+struct Relay {
+    validator: Option<(DynMsgDecoder, DynMsgEncoder)>,
+    stats: Arc<Stats>,
+}
+
+impl Relay {
+    fn new(validate: Option<FrameConfig>, stats: Arc<Stats>) -> Relay {
+        Relay {
+            validator: validate
+                .map(|layout| (DynMsgDecoder::new(layout), DynMsgEncoder::new(layout))),
+            stats,
+        }
+    }
+
+    /// Appends the bytes that should be forwarded to `out`.
+    fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        self.stats
+            .bytes
+            .fetch_add(input.len() as u64, Ordering::Relaxed);
+
+        let (decoder, encoder) = match &mut self.validator {
+            Some(validator) => validator,
+            None => {
+                out.extend_from_slice(input);
+                return;
+            }
+        };
+
+        for &byte in input {
+            match decoder.try_accept(byte) {
+                Some(Ok(mut msg)) => {
+                    // a valid frame encodes to the same bytes as long as the ID is kept
+                    msg.is_response = true;
+                    msg.encode(out, encoder)
+                        .expect("decoded frame does not fit its own layout");
+                    self.stats.frames.fetch_add(1, Ordering::Relaxed);
+                }
+                Some(Err(_)) => {
+                    self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Discards a partially received frame.
+    fn reset(&mut self) {
+        if let Some((decoder, _)) = &mut self.validator {
+            decoder.reset();
+        }
+    }
+}
+
+type Clients = Arc<Mutex<Vec<(u64, Arc<TcpStream>)>>>;
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("tinyframe-bridge: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(options) {
+        eprintln!("tinyframe-bridge: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(options: Options) -> io::Result<()> {
+    let mut device = serialport::new(&options.device, options.serial.baud_rate)
+        .data_bits(options.serial.data_bits)
+        .parity(options.serial.parity)
+        .stop_bits(options.serial.stop_bits)
+        .timeout(options.serial.timeout)
+        .open()
+        .map_err(|err| with_context(err.into(), &options.device))?;
+    let device_writer = Arc::new(Mutex::new(device.try_clone()?));
+
+    let listener =
+        TcpListener::bind(&options.listen).map_err(|err| with_context(err, &options.listen))?;
+    eprintln!(
+        "tinyframe-bridge: relaying {} <-> {}",
+        options.device,
+        listener.local_addr()?
+    );
+
+    let from_device = Arc::new(Stats::default());
+    let from_clients = Arc::new(Stats::default());
+    let clients = Clients::default();
+
+    if let Some(interval) = options.stats_interval {
+        let from_device = Arc::clone(&from_device);
+        let from_clients = Arc::clone(&from_clients);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            print_stats(&from_device, &from_clients);
+        });
+    }
+
+    {
+        let clients = Arc::clone(&clients);
+        let stats = Arc::clone(&from_clients);
+        let validate = options.validate;
+        thread::spawn(move || {
+            for (id, stream) in listener.incoming().enumerate() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        eprintln!("tinyframe-bridge: accept failed: {}", err);
+                        continue;
+                    }
+                };
+                // every client has its own decoder so that their partial frames do not mix
+                let relay = Relay::new(validate, Arc::clone(&stats));
+                if let Err(err) = add_client(id as u64, stream, relay, &clients, &device_writer) {
+                    eprintln!("tinyframe-bridge: failed to set up client: {}", err);
+                }
+            }
+        });
+    }
+
+    let mut relay = Relay::new(options.validate, Arc::clone(&from_device));
+    let result = relay_device(&mut *device, &mut relay, &clients);
+    print_stats(&from_device, &from_clients);
+    result
+}
+
+/// Registers a client and spawns a thread that relays its data to the device.
+fn add_client(
+    id: u64,
+    stream: TcpStream,
+    mut relay: Relay,
+    clients: &Clients,
+    device: &Arc<Mutex<Box<dyn serialport::SerialPort>>>,
+) -> io::Result<()> {
+    let peer = stream.peer_addr()?;
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT))?;
+    let mut reader = stream.try_clone()?;
+    clients.lock().unwrap().push((id, Arc::new(stream)));
+    eprintln!("tinyframe-bridge: {} connected", peer);
+
+    let clients = Arc::clone(clients);
+    let device = Arc::clone(device);
+    thread::spawn(move || {
+        let mut buf = [0; READ_BUFFER_SIZE];
+        let mut out = Vec::new();
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            };
+
+            out.clear();
+            relay.feed(&buf[..len], &mut out);
+            if out.is_empty() {
+                continue;
+            }
+
+            // write whole chunks so that frames from different clients are not interleaved
+            let mut device = device.lock().unwrap();
+            if let Err(err) = device.write_all(&out).and_then(|_| device.flush()) {
+                eprintln!("tinyframe-bridge: failed to write to device: {}", err);
+                break;
+            }
+        }
+
+        clients.lock().unwrap().retain(|(client, _)| *client != id);
+        eprintln!("tinyframe-bridge: {} disconnected", peer);
+    });
+    Ok(())
+}
+
+/// Relays data from the device to all clients until the device is closed.
+fn relay_device(
+    device: &mut dyn serialport::SerialPort,
+    relay: &mut Relay,
+    clients: &Clients,
+) -> io::Result<()> {
+    let mut buf = [0; READ_BUFFER_SIZE];
+    let mut out = Vec::new();
+    loop {
+        let len = match device.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                // like the parser timeout of upstream TinyFrame
+                relay.reset();
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        out.clear();
+        relay.feed(&buf[..len], &mut out);
+        if out.is_empty() {
+            continue;
+        }
+
+        // write outside of the lock, so that a slow client does not hold up the others
+        // connecting and disconnecting
+        let snapshot = clients.lock().unwrap().clone();
+        let mut failed = Vec::new();
+        for (id, client) in snapshot {
+            if (&*client).write_all(&out).is_err() {
+                // also stops the reading thread of the client
+                let _ = client.shutdown(Shutdown::Both);
+                failed.push(id);
+            }
+        }
+        if !failed.is_empty() {
+            clients
+                .lock()
+                .unwrap()
+                .retain(|(client, _)| !failed.contains(client));
+        }
+    }
+}
+
+/// Prefixes an error message with the device or address it is about.
+fn with_context(err: io::Error, what: &str) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {}", what, err))
+}
+
+fn print_stats(from_device: &Stats, from_clients: &Stats) {
+    eprintln!(
+        "tinyframe-bridge: device -> clients: {}; clients -> device: {}",
+        from_device, from_clients
+    );
+}
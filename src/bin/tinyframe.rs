@@ -0,0 +1,247 @@
+//! Command line tools for TinyFrame links.
+//!
+//! `tinyframe decode` reads a capture as hex text or raw bytes, runs it through a
+//! [DynMsgDecoder] with the frame layout given on the command line, and prints every frame with
+//! its fields, a hexdump of the payload and whether its checksums match. As responses cannot be
+//! told apart in a capture, the peer bit of the ID is shown in place of a response bit.
+
+#[macro_use]
+mod common;
+
+use common::{default_layout, parse_layout_arg};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+use tiny_frame::dynamic::{DecodeError, DynMsg, DynMsgDecoder, FrameConfig};
+
+const USAGE: &str = "\
+Usage: tinyframe <COMMAND> [OPTIONS]
+
+Commands:
+  decode    decode frames from a capture
+
+Run `tinyframe <COMMAND> --help` for the options of a command.";
+
+const DECODE_USAGE: &str = concat!(
+    "\
+Usage: tinyframe decode [OPTIONS] [FILE]
+
+Decodes frames from FILE, or from standard input if FILE is missing or `-`.
+
+Options:
+      --binary             read raw bytes instead of hex text
+",
+    layout_usage!(),
+    "  -h, --help               print this help
+
+Hex text may separate bytes with whitespace, commas or colons, and bytes may have a `0x`
+prefix.
+
+Frames do not say whether they are responses: a response reuses the ID of the message it
+answers, so a capture cannot tell it apart from a new message. Instead, the peer bit of the ID
+is shown, which is 1 if the ID was assigned by the master."
+);
+
+/// Payload bytes per hexdump line.
+const HEXDUMP_WIDTH: usize = 16;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("decode") => decode(args),
+        Some("-h") | Some("--help") | Some("help") => {
+            println!("{}", USAGE);
+            return;
+        }
+        Some(command) => Err(Error::Usage(format!("unknown command: {}", command), USAGE)),
+        None => Err(Error::Usage("missing command".into(), USAGE)),
+    };
+
+    match result {
+        Ok(()) => {}
+        Err(Error::Usage(err, usage)) => {
+            eprintln!("tinyframe: {}\n\n{}", err, usage);
+            process::exit(2);
+        }
+        Err(Error::Failed(err)) => {
+            eprintln!("tinyframe: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+enum Error {
+    /// The command line is invalid. Contains the usage text to print.
+    Usage(String, &'static str),
+
+    /// The command failed.
+    Failed(String),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Failed(err.to_string())
+    }
+}
+
+fn decode<I: Iterator<Item = String>>(mut args: I) -> Result<(), Error> {
+    let usage = |err| Error::Usage(err, DECODE_USAGE);
+    let mut binary = false;
+    let mut layout = default_layout();
+    let mut path = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", DECODE_USAGE);
+                return Ok(());
+            }
+            "--binary" => binary = true,
+            arg if parse_layout_arg(&mut layout, arg, &mut value).map_err(usage)? => {}
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(usage(format!("unknown option: {}", arg)))
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(usage(format!("unexpected argument: {}", arg))),
+        }
+    }
+
+    if !layout.is_valid() {
+        return Err(usage("invalid frame layout".into()));
+    }
+
+    let input = match path.as_deref() {
+        None | Some("-") => {
+            let mut input = Vec::new();
+            io::stdin().read_to_end(&mut input)?;
+            input
+        }
+        Some(path) => fs::read(path).map_err(|err| Error::Failed(format!("{}: {}", path, err)))?,
+    };
+    let bytes = if binary {
+        input
+    } else {
+        parse_hex(&String::from_utf8_lossy(&input)).map_err(Error::Failed)?
+    };
+
+    let stdout = io::stdout();
+    print_frames(&mut stdout.lock(), &bytes, layout)?;
+    Ok(())
+}
+
+/// Parses hex text into bytes.
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let tokens = text
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ':')
+        .filter(|token| !token.is_empty());
+
+    for token in tokens {
+        let digits = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        if digits.len() % 2 != 0 || !digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid hex: {}", token));
+        }
+
+        for i in (0..digits.len()).step_by(2) {
+            bytes.push(u8::from_str_radix(&digits[i..i + 2], 16).unwrap());
+        }
+    }
+    Ok(bytes)
+}
+
+/// Decodes all frames in `bytes` and prints them.
+fn print_frames<W: Write>(out: &mut W, bytes: &[u8], layout: FrameConfig) -> io::Result<()> {
+    let mut decoder = DynMsgDecoder::new(layout);
+    let mut frames = 0;
+    let mut errors = 0;
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        let result = match decoder.try_accept(byte) {
+            Some(result) => result,
+            None => continue,
+        };
+        frames += 1;
+
+        let (len, msg, error) = match result {
+            Ok(msg) => (frame_len(&layout, &msg), Some(msg), None),
+            Err(err) => {
+                let error = Some(err.to_string());
+                match err {
+                    DecodeError::HeadChecksum { .. } => (layout.head_size(), None, error),
+                    DecodeError::DataChecksum { msg, .. } => {
+                        (frame_len(&layout, &msg), Some(msg), error)
+                    }
+                }
+            }
+        };
+        if error.is_some() {
+            errors += 1;
+        }
+        let status = error.unwrap_or_else(|| "checksum ok".into());
+
+        write!(out, "frame {} at offset {}: ", frames, i + 1 - len)?;
+        match msg {
+            Some(msg) => {
+                writeln!(
+                    out,
+                    "id {:#0id_width$x}, peer bit {}, type {:#0type_width$x}, {} bytes, {}",
+                    msg.id,
+                    (msg.id & layout.master_bit() != 0) as u8,
+                    msg.msg_type,
+                    msg.data.len(),
+                    status,
+                    id_width = 2 + 2 * layout.id_bytes,
+                    type_width = 2 + 2 * layout.type_bytes,
+                )?;
+                hexdump(out, &msg.data)?;
+            }
+            None => writeln!(out, "{}", status)?,
+        }
+    }
+
+    writeln!(out, "{} frames, {} with checksum errors", frames, errors)
+}
+
+/// Returns the length of the encoded frame of a received message.
+fn frame_len(layout: &FrameConfig, msg: &DynMsg) -> usize {
+    if msg.data.is_empty() {
+        layout.head_size()
+    } else {
+        layout.head_size() + msg.data.len() + layout.checksum.size()
+    }
+}
+
+/// Prints a hexdump with an ASCII column.
+fn hexdump<W: Write>(out: &mut W, data: &[u8]) -> io::Result<()> {
+    for (line, chunk) in data.chunks(HEXDUMP_WIDTH).enumerate() {
+        write!(out, "    {:04x} ", line * HEXDUMP_WIDTH)?;
+        for i in 0..HEXDUMP_WIDTH {
+            match chunk.get(i) {
+                Some(byte) => write!(out, " {:02x}", byte)?,
+                None => write!(out, "   ")?,
+            }
+        }
+
+        let text: String = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        writeln!(out, "  |{}|", text)?;
+    }
+    Ok(())
+}
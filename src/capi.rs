@@ -0,0 +1,714 @@
+//! C bindings compatible with the upstream TinyFrame API.
+//!
+//! This module is only available with the `capi` feature. It exports `extern "C"` functions
+//! mirroring upstream TinyFrame, so that C code can link against this crate instead of
+//! `TinyFrame.c`. The frame layout is [DefaultFormat], which corresponds to the following
+//! upstream configuration:
+//!
+//! ```c
+//! #define TF_ID_BYTES 1
+//! #define TF_LEN_BYTES 2
+//! #define TF_TYPE_BYTES 1
+//! #define TF_CKSUM_TYPE TF_CKSUM_CRC16
+//! #define TF_USE_SOF_BYTE 1
+//! #define TF_SOF_BYTE 0x01
+//! #define TF_PARSER_TIMEOUT_TICKS 10
+//! ```
+//!
+//! The C header is `include/tiny_frame.h`, generated with
+//! `cbindgen --config cbindgen.toml --output include/tiny_frame.h`. To build a static library,
+//! run `cargo rustc --release --features capi --crate-type staticlib`.
+//!
+//! Unlike upstream, the `TinyFrame` struct is opaque: instances are created with [TF_Init] and
+//! freed with [TF_DeInit], and the user data is accessed with [TF_SetUserdata] and
+//! [TF_GetUserdata]. As upstream, the C side must define `TF_WriteImpl`, which is called with
+//! every encoded frame.
+//!
+//! # Examples
+//! The API can also be used from Rust, although this is mostly useful for testing:
+//!
+//! ```
+//! # #[cfg(feature = "capi")] {
+//! use std::sync::Mutex;
+//! use tiny_frame::capi::*;
+//!
+//! static WIRE: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+//! static RECEIVED: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+//!
+//! #[no_mangle]
+//! extern "C" fn TF_WriteImpl(_tf: *mut TinyFrame, buff: *const u8, len: u32) {
+//!     let bytes = unsafe { std::slice::from_raw_parts(buff, len as usize) };
+//!     WIRE.lock().unwrap().extend_from_slice(bytes);
+//! }
+//!
+//! unsafe extern "C" fn on_hello(_tf: *mut TinyFrame, msg: *mut TF_Msg) -> TF_Result {
+//!     let data = std::slice::from_raw_parts((*msg).data, (*msg).len as usize);
+//!     RECEIVED.lock().unwrap().extend_from_slice(data);
+//!     TF_Result::TF_STAY
+//! }
+//!
+//! unsafe {
+//!     let master = TF_Init(TF_Peer::TF_MASTER);
+//!     let slave = TF_Init(TF_Peer::TF_SLAVE);
+//!     TF_AddTypeListener(slave, 0x22, Some(on_hello));
+//!
+//!     assert!(TF_SendSimple(master, 0x22, b"hello".as_ptr(), 5));
+//!
+//!     let wire = std::mem::take(&mut *WIRE.lock().unwrap());
+//!     TF_Accept(slave, wire.as_ptr(), wire.len() as u32);
+//!     assert_eq!(*RECEIVED.lock().unwrap(), b"hello");
+//!
+//!     TF_DeInit(master);
+//!     TF_DeInit(slave);
+//! }
+//! # }
+//! ```
+
+#![allow(non_camel_case_types, non_snake_case)]
+
+use crate::{DefaultFormat, Msg, MsgDecoder, MsgEncoder};
+use std::os::raw::c_void;
+use std::{ptr, slice};
+
+/// Frame ID type.
+pub type TF_ID = u8;
+
+/// Frame length type.
+pub type TF_LEN = u16;
+
+/// Frame type type.
+pub type TF_TYPE = u8;
+
+/// Tick counter type used for timeouts.
+pub type TF_TICKS = u16;
+
+/// Number of ticks after which an incomplete frame is discarded.
+const PARSER_TIMEOUT_TICKS: TF_TICKS = 10;
+
+/// Peer bit.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TF_Peer {
+    TF_SLAVE = 0,
+    TF_MASTER = 1,
+}
+
+/// Listener result.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TF_Result {
+    /// Not handled, pass on to the next listener.
+    TF_NEXT = 0,
+    /// Handled, keep the listener.
+    TF_STAY = 1,
+    /// Handled, keep the listener and restart its timeout.
+    TF_RENEW = 2,
+    /// Handled, remove the listener.
+    TF_CLOSE = 3,
+}
+
+/// A message passed to and from the C API.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TF_Msg {
+    pub frame_id: TF_ID,
+    pub is_response: bool,
+    pub r#type: TF_TYPE,
+    pub data: *const u8,
+    pub len: TF_LEN,
+    pub userdata: *mut c_void,
+    pub userdata2: *mut c_void,
+}
+
+/// A message listener.
+pub type TF_Listener =
+    Option<unsafe extern "C" fn(tf: *mut TinyFrame, msg: *mut TF_Msg) -> TF_Result>;
+
+/// A listener timeout callback.
+pub type TF_Listener_Timeout = Option<unsafe extern "C" fn(tf: *mut TinyFrame) -> TF_Result>;
+
+// TinyFrame is only passed by pointer and is opaque to C
+#[allow(improper_ctypes)]
+extern "C" {
+    /// Writes encoded bytes to the transport. Must be defined by the C side.
+    fn TF_WriteImpl(tf: *mut TinyFrame, buff: *const u8, len: u32);
+}
+
+#[derive(Clone, Copy)]
+struct IdListener {
+    id: TF_ID,
+    func: unsafe extern "C" fn(*mut TinyFrame, *mut TF_Msg) -> TF_Result,
+    timeout_func: TF_Listener_Timeout,
+    timeout: TF_TICKS,
+    timeout_max: TF_TICKS,
+    userdata: *mut c_void,
+    userdata2: *mut c_void,
+}
+
+#[derive(Clone, Copy)]
+struct TypeListener {
+    msg_type: TF_TYPE,
+    func: unsafe extern "C" fn(*mut TinyFrame, *mut TF_Msg) -> TF_Result,
+}
+
+/// A listener slot. Every listener gets a unique token, so that a slot that was freed and
+/// reused by a listener callback is not mistaken for the listener that was called.
+type Slot<T> = Option<(u64, T)>;
+
+/// A TinyFrame instance.
+///
+/// Listeners are kept in slots that are cleared rather than removed, so that listeners may be
+/// added and removed from within listener callbacks.
+pub struct TinyFrame {
+    encoder: MsgEncoder<DefaultFormat>,
+    decoder: MsgDecoder<DefaultFormat>,
+    parser_timeout_ticks: TF_TICKS,
+    id_listeners: Vec<Slot<IdListener>>,
+    type_listeners: Vec<Slot<TypeListener>>,
+    generic_listeners: Vec<Slot<unsafe extern "C" fn(*mut TinyFrame, *mut TF_Msg) -> TF_Result>>,
+    next_token: u64,
+    userdata: *mut c_void,
+    usertag: u32,
+}
+
+/// Borrows the instance behind a handle. The borrow must not be held across listener calls.
+unsafe fn frame<'a>(tf: *mut TinyFrame) -> &'a mut TinyFrame {
+    &mut *tf
+}
+
+fn insert_slot<T>(slots: &mut Vec<Slot<T>>, next_token: &mut u64, value: T) {
+    let value = Some((*next_token, value));
+    *next_token += 1;
+
+    match slots.iter_mut().find(|slot| slot.is_none()) {
+        Some(slot) => *slot = value,
+        None => slots.push(value),
+    }
+}
+
+/// Returns the listener in slot `i` if it is still the one with the given token.
+fn slot_mut<T>(slots: &mut [Slot<T>], i: usize, token: u64) -> Option<&mut T> {
+    match slots.get_mut(i) {
+        Some(Some((slot_token, value))) if *slot_token == token => Some(value),
+        _ => None,
+    }
+}
+
+/// Removes the listener in slot `i` if it is still the one with the given token.
+fn take_slot<T>(slots: &mut [Slot<T>], i: usize, token: u64) -> Option<T> {
+    slot_mut(slots, i, token)?;
+    slots[i].take().map(|(_, value)| value)
+}
+
+/// Creates a new TinyFrame instance.
+#[no_mangle]
+pub extern "C" fn TF_Init(peer_bit: TF_Peer) -> *mut TinyFrame {
+    let mut encoder = MsgEncoder::new();
+    encoder.is_master = peer_bit == TF_Peer::TF_MASTER;
+
+    Box::into_raw(Box::new(TinyFrame {
+        encoder,
+        decoder: MsgDecoder::new(),
+        parser_timeout_ticks: 0,
+        id_listeners: Vec::new(),
+        type_listeners: Vec::new(),
+        generic_listeners: Vec::new(),
+        next_token: 0,
+        userdata: ptr::null_mut(),
+        usertag: 0,
+    }))
+}
+
+/// Frees a TinyFrame instance.
+///
+/// # Safety
+/// `tf` must have been returned by [TF_Init] and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn TF_DeInit(tf: *mut TinyFrame) {
+    if !tf.is_null() {
+        drop(Box::from_raw(tf));
+    }
+}
+
+/// Sets the user data pointer of the instance.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_SetUserdata(tf: *mut TinyFrame, userdata: *mut c_void, usertag: u32) {
+    frame(tf).userdata = userdata;
+    frame(tf).usertag = usertag;
+}
+
+/// Returns the user data pointer of the instance.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_GetUserdata(tf: *mut TinyFrame) -> *mut c_void {
+    frame(tf).userdata
+}
+
+/// Returns the user tag of the instance.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_GetUsertag(tf: *mut TinyFrame) -> u32 {
+    frame(tf).usertag
+}
+
+/// Resets the message parser, discarding any partially received frame.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_ResetParser(tf: *mut TinyFrame) {
+    frame(tf).decoder.reset();
+}
+
+/// Accepts received bytes. Listeners are called for every complete frame.
+///
+/// # Safety
+/// `tf` must be a valid instance and `buffer` must point to `count` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn TF_Accept(tf: *mut TinyFrame, buffer: *const u8, count: u32) {
+    if count == 0 {
+        return;
+    }
+    for &byte in slice::from_raw_parts(buffer, count as usize) {
+        TF_AcceptChar(tf, byte);
+    }
+}
+
+/// Accepts a single received byte.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_AcceptChar(tf: *mut TinyFrame, c: u8) {
+    let msg = {
+        let frame = frame(tf);
+        if frame.parser_timeout_ticks >= PARSER_TIMEOUT_TICKS {
+            frame.decoder.reset();
+        }
+        frame.parser_timeout_ticks = 0;
+        frame.decoder.accept(c)
+    };
+
+    if let Some(msg) = msg {
+        handle_received(tf, msg);
+    }
+}
+
+/// Calls the listeners for a received message. No references into `tf` are held while calling
+/// listeners, since they may call back into the API.
+unsafe fn handle_received(tf: *mut TinyFrame, received: Msg<DefaultFormat>) {
+    let mut msg = TF_Msg {
+        frame_id: received.id,
+        is_response: false,
+        r#type: received.msg_type,
+        data: received.data.as_ptr(),
+        len: received.data.len() as TF_LEN,
+        userdata: ptr::null_mut(),
+        userdata2: ptr::null_mut(),
+    };
+
+    let mut i = 0;
+    while let Some(&slot) = frame(tf).id_listeners.get(i) {
+        if let Some((token, lst)) = slot.filter(|(_, lst)| lst.id == msg.frame_id) {
+            msg.userdata = lst.userdata;
+            msg.userdata2 = lst.userdata2;
+            let res = (lst.func)(tf, &mut msg);
+
+            // the listener may have removed itself, and another one may have taken its slot
+            if let Some(lst) = slot_mut(&mut frame(tf).id_listeners, i, token) {
+                lst.userdata = msg.userdata;
+                lst.userdata2 = msg.userdata2;
+            }
+
+            if res != TF_Result::TF_NEXT {
+                match res {
+                    TF_Result::TF_CLOSE => cleanup_id_listener(tf, i, token),
+                    TF_Result::TF_RENEW => {
+                        if let Some(lst) = slot_mut(&mut frame(tf).id_listeners, i, token) {
+                            lst.timeout = lst.timeout_max;
+                        }
+                    }
+                    _ => (),
+                }
+                return;
+            }
+        }
+        i += 1;
+    }
+
+    msg.userdata = ptr::null_mut();
+    msg.userdata2 = ptr::null_mut();
+
+    let mut i = 0;
+    while let Some(&slot) = frame(tf).type_listeners.get(i) {
+        if let Some((token, lst)) = slot.filter(|(_, lst)| lst.msg_type == msg.r#type) {
+            let res = (lst.func)(tf, &mut msg);
+            if res != TF_Result::TF_NEXT {
+                if res == TF_Result::TF_CLOSE {
+                    take_slot(&mut frame(tf).type_listeners, i, token);
+                }
+                return;
+            }
+        }
+        i += 1;
+    }
+
+    let mut i = 0;
+    while let Some(&slot) = frame(tf).generic_listeners.get(i) {
+        if let Some((token, func)) = slot {
+            let res = func(tf, &mut msg);
+            if res != TF_Result::TF_NEXT {
+                if res == TF_Result::TF_CLOSE {
+                    take_slot(&mut frame(tf).generic_listeners, i, token);
+                }
+                return;
+            }
+        }
+        i += 1;
+    }
+}
+
+/// Removes an ID listener, unless its slot has been reused. If it has user data, the listener is
+/// called one last time with a null data pointer so it can free it.
+unsafe fn cleanup_id_listener(tf: *mut TinyFrame, i: usize, token: u64) {
+    let lst = match take_slot(&mut frame(tf).id_listeners, i, token) {
+        Some(lst) => lst,
+        None => return,
+    };
+
+    if !lst.userdata.is_null() || !lst.userdata2.is_null() {
+        let mut msg = TF_Msg {
+            frame_id: lst.id,
+            is_response: false,
+            r#type: 0,
+            data: ptr::null(),
+            len: 0,
+            userdata: lst.userdata,
+            userdata2: lst.userdata2,
+        };
+        (lst.func)(tf, &mut msg);
+    }
+}
+
+/// Adds a listener for messages with the ID of the given message. `timeout` is the number of
+/// ticks after which the listener is removed, or 0 for no timeout.
+///
+/// # Safety
+/// `tf` must be a valid instance and `msg` must point to a valid message.
+#[no_mangle]
+pub unsafe extern "C" fn TF_AddIdListener(
+    tf: *mut TinyFrame,
+    msg: *mut TF_Msg,
+    cb: TF_Listener,
+    ftimeout: TF_Listener_Timeout,
+    timeout: TF_TICKS,
+) -> bool {
+    let func = match cb {
+        Some(func) => func,
+        None => return false,
+    };
+
+    let frame = frame(tf);
+    insert_slot(
+        &mut frame.id_listeners,
+        &mut frame.next_token,
+        IdListener {
+            id: (*msg).frame_id,
+            func,
+            timeout_func: ftimeout,
+            timeout,
+            timeout_max: timeout,
+            userdata: (*msg).userdata,
+            userdata2: (*msg).userdata2,
+        },
+    );
+    true
+}
+
+/// Removes the listener for the given ID.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_RemoveIdListener(tf: *mut TinyFrame, frame_id: TF_ID) -> bool {
+    let found = frame(tf)
+        .id_listeners
+        .iter()
+        .enumerate()
+        .find_map(|(i, slot)| match slot {
+            Some((token, lst)) if lst.id == frame_id => Some((i, *token)),
+            _ => None,
+        });
+
+    match found {
+        Some((i, token)) => {
+            cleanup_id_listener(tf, i, token);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Restarts the timeout of the listener for the given ID.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_RenewIdListener(tf: *mut TinyFrame, id: TF_ID) -> bool {
+    for (_, lst) in frame(tf).id_listeners.iter_mut().flatten() {
+        if lst.id == id {
+            lst.timeout = lst.timeout_max;
+            return true;
+        }
+    }
+    false
+}
+
+/// Adds a listener for messages of the given type.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_AddTypeListener(
+    tf: *mut TinyFrame,
+    frame_type: TF_TYPE,
+    cb: TF_Listener,
+) -> bool {
+    match cb {
+        Some(func) => {
+            let frame = frame(tf);
+            insert_slot(
+                &mut frame.type_listeners,
+                &mut frame.next_token,
+                TypeListener {
+                    msg_type: frame_type,
+                    func,
+                },
+            );
+            true
+        }
+        None => false,
+    }
+}
+
+/// Removes the listener for the given type.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_RemoveTypeListener(tf: *mut TinyFrame, type_: TF_TYPE) -> bool {
+    for slot in frame(tf).type_listeners.iter_mut() {
+        if matches!(slot, Some((_, lst)) if lst.msg_type == type_) {
+            *slot = None;
+            return true;
+        }
+    }
+    false
+}
+
+/// Adds a listener for all messages not handled by an ID or type listener.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_AddGenericListener(tf: *mut TinyFrame, cb: TF_Listener) -> bool {
+    match cb {
+        Some(func) => {
+            let frame = frame(tf);
+            insert_slot(&mut frame.generic_listeners, &mut frame.next_token, func);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Removes a generic listener.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_RemoveGenericListener(tf: *mut TinyFrame, cb: TF_Listener) -> bool {
+    for slot in frame(tf).generic_listeners.iter_mut() {
+        // listeners are identified by address, as in upstream
+        if slot.is_some() && slot.map(|(_, f)| f as usize) == cb.map(|f| f as usize) {
+            *slot = None;
+            return true;
+        }
+    }
+    false
+}
+
+/// Encodes a message and passes it to `TF_WriteImpl`. If the message is not a response, a new
+/// ID is assigned and stored in `msg->frame_id`.
+unsafe fn send(tf: *mut TinyFrame, msg: *mut TF_Msg) -> bool {
+    let data = if (*msg).len == 0 || (*msg).data.is_null() {
+        Vec::new()
+    } else {
+        slice::from_raw_parts((*msg).data, (*msg).len as usize).to_vec()
+    };
+
+    let out_msg: Msg<DefaultFormat> = Msg {
+        id: (*msg).frame_id,
+        is_response: (*msg).is_response,
+        msg_type: (*msg).r#type,
+        data,
+    };
+
+    let mut buf = Vec::new();
+    match out_msg.encode(&mut buf, &mut frame(tf).encoder) {
+        Ok(id) => {
+            (*msg).frame_id = id;
+            TF_WriteImpl(tf, buf.as_ptr(), buf.len() as u32);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Sends a message.
+///
+/// # Safety
+/// `tf` must be a valid instance and `msg` must point to a valid message whose `data` points to
+/// `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn TF_Send(tf: *mut TinyFrame, msg: *mut TF_Msg) -> bool {
+    send(tf, msg)
+}
+
+/// Sends a message with the given type and data.
+///
+/// # Safety
+/// `tf` must be a valid instance and `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn TF_SendSimple(
+    tf: *mut TinyFrame,
+    type_: TF_TYPE,
+    data: *const u8,
+    len: TF_LEN,
+) -> bool {
+    let mut msg = TF_Msg {
+        frame_id: 0,
+        is_response: false,
+        r#type: type_,
+        data,
+        len,
+        userdata: ptr::null_mut(),
+        userdata2: ptr::null_mut(),
+    };
+    send(tf, &mut msg)
+}
+
+/// Sends a message and adds an ID listener for the response.
+///
+/// # Safety
+/// `tf` must be a valid instance and `msg` must point to a valid message whose `data` points to
+/// `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn TF_Query(
+    tf: *mut TinyFrame,
+    msg: *mut TF_Msg,
+    listener: TF_Listener,
+    ftimeout: TF_Listener_Timeout,
+    timeout: TF_TICKS,
+) -> bool {
+    if !send(tf, msg) {
+        return false;
+    }
+    if listener.is_some() {
+        TF_AddIdListener(tf, msg, listener, ftimeout, timeout);
+    }
+    true
+}
+
+/// Sends a message with the given type and data and adds an ID listener for the response.
+///
+/// # Safety
+/// `tf` must be a valid instance and `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn TF_QuerySimple(
+    tf: *mut TinyFrame,
+    type_: TF_TYPE,
+    data: *const u8,
+    len: TF_LEN,
+    listener: TF_Listener,
+    ftimeout: TF_Listener_Timeout,
+    timeout: TF_TICKS,
+) -> bool {
+    let mut msg = TF_Msg {
+        frame_id: 0,
+        is_response: false,
+        r#type: type_,
+        data,
+        len,
+        userdata: ptr::null_mut(),
+        userdata2: ptr::null_mut(),
+    };
+    TF_Query(tf, &mut msg, listener, ftimeout, timeout)
+}
+
+/// Sends a response to a received message, reusing its ID.
+///
+/// # Safety
+/// `tf` must be a valid instance and `msg` must point to a valid message whose `data` points to
+/// `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn TF_Respond(tf: *mut TinyFrame, msg: *mut TF_Msg) -> bool {
+    (*msg).is_response = true;
+    send(tf, msg)
+}
+
+/// Advances timeouts by one tick. Should be called periodically.
+///
+/// # Safety
+/// `tf` must be a valid instance.
+#[no_mangle]
+pub unsafe extern "C" fn TF_Tick(tf: *mut TinyFrame) {
+    if frame(tf).parser_timeout_ticks < PARSER_TIMEOUT_TICKS {
+        frame(tf).parser_timeout_ticks += 1;
+    }
+
+    let mut i = 0;
+    while let Some(&slot) = frame(tf).id_listeners.get(i) {
+        if let Some((token, lst)) = slot.filter(|(_, lst)| lst.timeout > 0) {
+            let timeout = lst.timeout - 1;
+            if let Some(lst) = slot_mut(&mut frame(tf).id_listeners, i, token) {
+                lst.timeout = timeout;
+            }
+
+            if timeout == 0 {
+                if let Some(timeout_func) = lst.timeout_func {
+                    timeout_func(tf);
+                }
+                cleanup_id_listener(tf, i, token);
+            }
+        }
+        i += 1;
+    }
+}
+
+/// Resets a message to all zeros.
+///
+/// # Safety
+/// `msg` must point to a writable message.
+#[no_mangle]
+pub unsafe extern "C" fn TF_ClearMsg(msg: *mut TF_Msg) {
+    ptr::write(
+        msg,
+        TF_Msg {
+            frame_id: 0,
+            is_response: false,
+            r#type: 0,
+            data: ptr::null(),
+            len: 0,
+            userdata: ptr::null_mut(),
+            userdata2: ptr::null_mut(),
+        },
+    );
+}
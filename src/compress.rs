@@ -0,0 +1,373 @@
+//! Payload compression.
+//!
+//! [Compression] compresses the payloads of selected message types with a small LZ77 codec in
+//! the style of LZ4, and decompresses them on the receiving side, where [CompressDecoder] does
+//! this for every received message. Both peers must enable the same message types. The codec
+//! itself ([compress] and [decompress]) only works on slices and `Vec`s, so it can be used
+//! without `std`.
+//!
+//! Payloads of enabled message types start with a method byte: [RAW] for payloads sent
+//! unchanged (used when compression does not make them smaller), or [LZ] for compressed
+//! payloads, which continue with the big endian `u32` decompressed size and the compressed
+//! data. Payloads of other message types are not changed.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use tiny_frame::compress::{CompressDecoder, Compression};
+//!
+//! const LOG_DUMP: u8 = 0x60;
+//!
+//! let mut compression = Compression::<DefaultFormat>::new(64 * 1024);
+//! compression.enable(LOG_DUMP);
+//!
+//! let log = b"sensor ok\n".repeat(100);
+//! let msg = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: LOG_DUMP,
+//!     data: log.clone(),
+//! };
+//!
+//! let compressed = compression.compress_msg(msg);
+//! assert!(compressed.data.len() < 100);
+//!
+//! let mut frame = Vec::new();
+//! compressed.encode(&mut frame, &mut MsgEncoder::new()).unwrap();
+//!
+//! let mut decoder = CompressDecoder::new(MsgDecoder::new(), compression);
+//! let received = frame.iter().find_map(|&b| decoder.accept(b)).unwrap();
+//! assert_eq!(received.unwrap().data, log);
+//! ```
+
+use crate::{FrameFormat, Msg, MsgDecoder};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Method byte of payloads sent without compression.
+pub const RAW: u8 = 0;
+
+/// Method byte of compressed payloads.
+pub const LZ: u8 = 1;
+
+const MIN_MATCH: usize = 4;
+const HASH_BITS: u32 = 12;
+
+/// Errors returned when decompressing a payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressError {
+    /// The payload has no method byte or size.
+    Truncated,
+
+    /// The method byte is unknown.
+    UnknownMethod(u8),
+
+    /// The decompressed size exceeds the limit.
+    TooLarge,
+
+    /// The compressed data is invalid.
+    Corrupt,
+}
+
+impl fmt::Display for CompressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompressError::Truncated => write!(f, "compressed payload is truncated"),
+            CompressError::UnknownMethod(method) => {
+                write!(f, "unknown compression method {}", method)
+            }
+            CompressError::TooLarge => write!(f, "decompressed payload is too large"),
+            CompressError::Corrupt => write!(f, "compressed payload is corrupt"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompressError {}
+
+/// Compression settings for a frame format.
+pub struct Compression<F: FrameFormat> {
+    types: Vec<F::Type>,
+    max_size: usize,
+}
+
+impl<F: FrameFormat> Compression<F> {
+    /// Creates new compression settings with no message types enabled. Received payloads that
+    /// would decompress to more than `max_size` bytes are rejected.
+    pub fn new(max_size: usize) -> Compression<F> {
+        Compression {
+            types: Vec::new(),
+            max_size,
+        }
+    }
+
+    /// Enables compression for a message type.
+    pub fn enable(&mut self, msg_type: F::Type) {
+        if !self.is_enabled(msg_type) {
+            self.types.push(msg_type);
+        }
+    }
+
+    /// Returns true if compression is enabled for a message type.
+    pub fn is_enabled(&self, msg_type: F::Type) -> bool {
+        self.types.contains(&msg_type)
+    }
+
+    /// Compresses the payload of a message if compression is enabled for its type.
+    pub fn compress_msg(&self, mut msg: Msg<F>) -> Msg<F> {
+        if !self.is_enabled(msg.msg_type) {
+            return msg;
+        }
+
+        let compressed = compress(&msg.data);
+        let mut data;
+        if compressed.len() + 4 < msg.data.len() && msg.data.len() <= u32::MAX as usize {
+            data = Vec::with_capacity(compressed.len() + 5);
+            data.push(LZ);
+            data.extend_from_slice(&(msg.data.len() as u32).to_be_bytes());
+            data.extend_from_slice(&compressed);
+        } else {
+            data = Vec::with_capacity(msg.data.len() + 1);
+            data.push(RAW);
+            data.extend_from_slice(&msg.data);
+        }
+
+        msg.data = data;
+        msg
+    }
+
+    /// Decompresses the payload of a message if compression is enabled for its type.
+    pub fn decompress_msg(&self, mut msg: Msg<F>) -> Result<Msg<F>, CompressError> {
+        if !self.is_enabled(msg.msg_type) {
+            return Ok(msg);
+        }
+
+        let (&method, rest) = msg.data.split_first().ok_or(CompressError::Truncated)?;
+        msg.data = match method {
+            RAW if rest.len() > self.max_size => return Err(CompressError::TooLarge),
+            RAW => rest.to_vec(),
+            LZ => {
+                if rest.len() < 4 {
+                    return Err(CompressError::Truncated);
+                }
+                let size = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+                if size > self.max_size {
+                    return Err(CompressError::TooLarge);
+                }
+                decompress(&rest[4..], size)?
+            }
+            method => return Err(CompressError::UnknownMethod(method)),
+        };
+        Ok(msg)
+    }
+}
+
+impl<F: FrameFormat> Clone for Compression<F> {
+    fn clone(&self) -> Compression<F> {
+        Compression {
+            types: self.types.clone(),
+            max_size: self.max_size,
+        }
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for Compression<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Compression")
+            .field("types", &self.types.len())
+            .field("max_size", &self.max_size)
+            .finish()
+    }
+}
+
+/// A message decoder that decompresses received messages.
+pub struct CompressDecoder<F: FrameFormat> {
+    decoder: MsgDecoder<F>,
+    compression: Compression<F>,
+}
+
+impl<F: FrameFormat> CompressDecoder<F> {
+    /// Creates a new decoder with the given compression settings.
+    pub fn new(decoder: MsgDecoder<F>, compression: Compression<F>) -> CompressDecoder<F> {
+        CompressDecoder {
+            decoder,
+            compression,
+        }
+    }
+
+    /// Returns the inner decoder.
+    pub fn decoder(&mut self) -> &mut MsgDecoder<F> {
+        &mut self.decoder
+    }
+
+    /// Returns the compression settings.
+    pub fn compression(&mut self) -> &mut Compression<F> {
+        &mut self.compression
+    }
+
+    /// Accepts a byte. Returns the decompressed message once a frame is complete, or an error if
+    /// its payload could not be decompressed. Frames that fail to decode are dropped, as with
+    /// [MsgDecoder::accept].
+    pub fn accept(&mut self, byte: u8) -> Option<Result<Msg<F>, CompressError>> {
+        let compression = &self.compression;
+        self.decoder
+            .accept(byte)
+            .map(|msg| compression.decompress_msg(msg))
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for CompressDecoder<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CompressDecoder")
+            .field("compression", &self.compression)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Compresses data.
+///
+/// The output is a sequence of LZ4-style sequences: a token byte with the literal length in the
+/// high and the match length minus 4 in the low nibble (15 meaning that more length bytes
+/// follow), the literals, and the little endian `u16` match offset. The last sequence has no
+/// match.
+///
+/// # Examples
+/// ```
+/// # use tiny_frame::compress::{compress, decompress};
+/// let data = b"abcabcabcabcabcabc";
+/// let compressed = compress(data);
+/// assert!(compressed.len() < data.len());
+/// assert_eq!(decompress(&compressed, data.len()), Ok(data.to_vec()));
+/// ```
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 16);
+    // positions are stored plus one, so that 0 means empty
+    let mut table = vec![0usize; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut i = 0;
+
+    while i + MIN_MATCH <= input.len() {
+        let seq = u32::from_le_bytes([input[i], input[i + 1], input[i + 2], input[i + 3]]);
+        let hash = (seq.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+        let candidate = table[hash];
+        table[hash] = i + 1;
+
+        if candidate > 0 {
+            let start = candidate - 1;
+            if i - start <= u16::MAX as usize
+                && input[start..start + MIN_MATCH] == input[i..i + MIN_MATCH]
+            {
+                let mut len = MIN_MATCH;
+                while i + len < input.len() && input[start + len] == input[i + len] {
+                    len += 1;
+                }
+
+                write_sequence(&mut out, &input[anchor..i], Some(((i - start) as u16, len)));
+                i += len;
+                anchor = i;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    write_sequence(&mut out, &input[anchor..], None);
+    out
+}
+
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(u16, usize)>) {
+    let match_len = matched.map_or(0, |(_, len)| len - MIN_MATCH);
+    out.push(((literals.len().min(15) as u8) << 4) | match_len.min(15) as u8);
+
+    if literals.len() >= 15 {
+        write_len(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+
+    if let Some((offset, _)) = matched {
+        out.extend_from_slice(&offset.to_le_bytes());
+        if match_len >= 15 {
+            write_len(out, match_len - 15);
+        }
+    }
+}
+
+fn write_len(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+/// Decompresses data produced by [compress]. `size` is the size of the decompressed data;
+/// decompression fails if the data does not decompress to exactly that size.
+pub fn decompress(input: &[u8], size: usize) -> Result<Vec<u8>, CompressError> {
+    let mut out = Vec::with_capacity(size);
+    let mut pos = 0;
+
+    while pos < input.len() {
+        let token = input[pos];
+        pos += 1;
+
+        let mut literals = (token >> 4) as usize;
+        if literals == 15 {
+            literals += read_len(input, &mut pos)?;
+        }
+        if literals > input.len() - pos || literals > size - out.len() {
+            return Err(CompressError::Corrupt);
+        }
+        out.extend_from_slice(&input[pos..pos + literals]);
+        pos += literals;
+
+        // the last sequence has no match
+        if pos == input.len() {
+            break;
+        }
+
+        if input.len() - pos < 2 {
+            return Err(CompressError::Corrupt);
+        }
+        let offset = u16::from_le_bytes([input[pos], input[pos + 1]]) as usize;
+        pos += 2;
+        if offset == 0 || offset > out.len() {
+            return Err(CompressError::Corrupt);
+        }
+
+        let mut len = (token & 0x0f) as usize;
+        if len == 15 {
+            len += read_len(input, &mut pos)?;
+        }
+        len += MIN_MATCH;
+        if len > size - out.len() {
+            return Err(CompressError::Corrupt);
+        }
+
+        // matches may overlap the bytes they produce, so copy byte by byte
+        let start = out.len() - offset;
+        for i in 0..len {
+            let byte = out[start + i];
+            out.push(byte);
+        }
+    }
+
+    if out.len() != size {
+        return Err(CompressError::Corrupt);
+    }
+    Ok(out)
+}
+
+fn read_len(input: &[u8], pos: &mut usize) -> Result<usize, CompressError> {
+    let mut len = 0usize;
+    loop {
+        let byte = *input.get(*pos).ok_or(CompressError::Corrupt)?;
+        *pos += 1;
+        len = len
+            .checked_add(byte as usize)
+            .ok_or(CompressError::Corrupt)?;
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Datagram transport, with exactly one frame per datagram.
+//!
+//! Datagram sockets preserve message boundaries, so there is no need to hunt for the start of a
+//! frame. [decode_frame] parses a single complete frame and rejects datagrams with a missing
+//! start byte, a truncated frame or trailing bytes. With the `std` feature, `UdpTransport` sends
+//! every message as its own UDP datagram and routes replies back to the address the request came
+//! from.
+
+use crate::{DecodeError, FrameFormat, Msg, MsgDecoder};
+use core::fmt;
+
+#[cfg(feature = "std")]
+pub use self::udp::UdpTransport;
+
+/// Errors returned by [decode_frame].
+pub enum DatagramError<F: FrameFormat> {
+    /// The frame does not start with the start byte.
+    MissingSof,
+
+    /// The frame ended early.
+    Truncated,
+
+    /// There are bytes after the end of the frame.
+    TrailingBytes(usize),
+
+    /// The frame could not be decoded.
+    Decode(DecodeError<F>),
+}
+
+impl<F: FrameFormat> fmt::Debug for DatagramError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatagramError::MissingSof => write!(f, "MissingSof"),
+            DatagramError::Truncated => write!(f, "Truncated"),
+            DatagramError::TrailingBytes(len) => f.debug_tuple("TrailingBytes").field(len).finish(),
+            DatagramError::Decode(err) => f.debug_tuple("Decode").field(err).finish(),
+        }
+    }
+}
+
+impl<F: FrameFormat> Clone for DatagramError<F> {
+    fn clone(&self) -> DatagramError<F> {
+        match self {
+            DatagramError::MissingSof => DatagramError::MissingSof,
+            DatagramError::Truncated => DatagramError::Truncated,
+            DatagramError::TrailingBytes(len) => DatagramError::TrailingBytes(*len),
+            DatagramError::Decode(err) => DatagramError::Decode(err.clone()),
+        }
+    }
+}
+
+impl<F: FrameFormat> PartialEq for DatagramError<F> {
+    fn eq(&self, other: &DatagramError<F>) -> bool {
+        match (self, other) {
+            (DatagramError::MissingSof, DatagramError::MissingSof)
+            | (DatagramError::Truncated, DatagramError::Truncated) => true,
+            (DatagramError::TrailingBytes(a), DatagramError::TrailingBytes(b)) => a == b,
+            (DatagramError::Decode(a), DatagramError::Decode(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<F: FrameFormat> fmt::Display for DatagramError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatagramError::MissingSof => write!(f, "frame does not start with the start byte"),
+            DatagramError::Truncated => write!(f, "frame is truncated"),
+            DatagramError::TrailingBytes(len) => {
+                write!(f, "{} trailing bytes after the frame", len)
+            }
+            DatagramError::Decode(err) => err.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: FrameFormat> std::error::Error for DatagramError<F> {}
+
+/// Decodes a buffer that contains exactly one frame.
+///
+/// # Examples
+/// ```
+/// # use tiny_frame::*;
+/// use tiny_frame::datagram::{decode_frame, DatagramError};
+///
+/// let msg = Msg {
+///     id: 0,
+///     is_response: false,
+///     msg_type: 0x12,
+///     data: b"ping".to_vec(),
+/// };
+/// let mut frame = Vec::new();
+/// msg.clone().encode(&mut frame, &mut MsgEncoder::new()).unwrap();
+///
+/// assert_eq!(decode_frame::<DefaultFormat>(&frame), Ok(msg));
+/// assert_eq!(
+///     decode_frame::<DefaultFormat>(&frame[..frame.len() - 1]),
+///     Err(DatagramError::Truncated)
+/// );
+///
+/// frame.push(0);
+/// assert_eq!(
+///     decode_frame::<DefaultFormat>(&frame),
+///     Err(DatagramError::TrailingBytes(1))
+/// );
+/// ```
+pub fn decode_frame<F: FrameFormat>(frame: &[u8]) -> Result<Msg<F>, DatagramError<F>> {
+    if let Some(sof_byte) = F::SOF {
+        if frame.first() != Some(&sof_byte) {
+            return Err(DatagramError::MissingSof);
+        }
+    }
+
+    let mut decoder = MsgDecoder::new();
+    for (i, &byte) in frame.iter().enumerate() {
+        match decoder.try_accept(byte) {
+            Some(Ok(msg)) if i + 1 == frame.len() => return Ok(msg),
+            Some(Ok(_)) => return Err(DatagramError::TrailingBytes(frame.len() - i - 1)),
+            Some(Err(err)) => return Err(DatagramError::Decode(err)),
+            None => {}
+        }
+    }
+    Err(DatagramError::Truncated)
+}
+
+#[cfg(feature = "std")]
+mod udp {
+    use super::{decode_frame, DatagramError};
+    use crate::{FrameFormat, Msg, MsgEncoder};
+    use std::fmt;
+    use std::io;
+    use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+    type DecodeResult<F> = Result<Msg<F>, DatagramError<F>>;
+
+    /// The largest possible UDP payload.
+    const MAX_DATAGRAM_SIZE: usize = 65_535;
+
+    /// A TinyFrame connection over a UDP socket, with one frame per datagram.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::*;
+    /// use std::net::UdpSocket;
+    /// use tiny_frame::datagram::UdpTransport;
+    ///
+    /// let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let server_addr = server.local_addr().unwrap();
+    /// let mut server = UdpTransport::<DefaultFormat>::new(server, MsgEncoder::new());
+    ///
+    /// let mut encoder = MsgEncoder::new();
+    /// encoder.is_master = true;
+    /// let mut client =
+    ///     UdpTransport::<DefaultFormat>::new(UdpSocket::bind("127.0.0.1:0").unwrap(), encoder);
+    ///
+    /// let msg = Msg {
+    ///     id: 0,
+    ///     is_response: false,
+    ///     msg_type: 0x12,
+    ///     data: b"ping".to_vec(),
+    /// };
+    /// let id = client.send_to(msg, server_addr).unwrap();
+    ///
+    /// let (request, source) = server.recv_from().unwrap();
+    /// server
+    ///     .send_to(request.create_response(0x13, b"pong".to_vec()), source)
+    ///     .unwrap();
+    ///
+    /// let (reply, _) = client.recv_from().unwrap();
+    /// assert_eq!(reply.id, id);
+    /// assert_eq!(reply.data, b"pong");
+    /// ```
+    pub struct UdpTransport<F: FrameFormat> {
+        socket: UdpSocket,
+        encoder: MsgEncoder<F>,
+        buf: Vec<u8>,
+    }
+
+    impl<F: FrameFormat> UdpTransport<F> {
+        /// Creates a new transport over a bound socket.
+        pub fn new(socket: UdpSocket, encoder: MsgEncoder<F>) -> UdpTransport<F> {
+            UdpTransport {
+                socket,
+                encoder,
+                buf: vec![0; MAX_DATAGRAM_SIZE],
+            }
+        }
+
+        /// Returns the socket.
+        pub fn socket(&self) -> &UdpSocket {
+            &self.socket
+        }
+
+        /// Returns the encoder.
+        pub fn encoder(&mut self) -> &mut MsgEncoder<F> {
+            &mut self.encoder
+        }
+
+        /// Sends a message in a single datagram. If the message is not a response, a new ID will
+        /// be assigned. Returns the ID of the message.
+        pub fn send_to<A: ToSocketAddrs>(&mut self, msg: Msg<F>, addr: A) -> io::Result<F::Id> {
+            let mut frame = Vec::new();
+            let id = msg.encode(&mut frame, &mut self.encoder)?;
+            if self.socket.send_to(&frame, addr)? != frame.len() {
+                return Err(io::Error::other("frame was not sent in one datagram"));
+            }
+            Ok(id)
+        }
+
+        /// Receives the next valid frame and the address it came from. Invalid datagrams are
+        /// dropped; use [try_recv_from](Self::try_recv_from) to find out about them.
+        pub fn recv_from(&mut self) -> io::Result<(Msg<F>, SocketAddr)> {
+            loop {
+                if let (Ok(msg), source) = self.try_recv_from()? {
+                    return Ok((msg, source));
+                }
+            }
+        }
+
+        /// Receives the next datagram and decodes it with [decode_frame](super::decode_frame).
+        /// Returns the result and the address the datagram came from.
+        pub fn try_recv_from(&mut self) -> io::Result<(DecodeResult<F>, SocketAddr)> {
+            let (len, source) = self.socket.recv_from(&mut self.buf)?;
+            Ok((decode_frame(&self.buf[..len]), source))
+        }
+
+        /// Receives messages and passes them to `handler` until the socket fails. If the handler
+        /// returns a message, it is sent back to the address the request came from.
+        ///
+        /// Read timeouts of the socket are waited out, but the socket must be blocking: on a
+        /// non-blocking socket without a read timeout, this fails with
+        /// [WouldBlock](io::ErrorKind::WouldBlock) once no datagram is available.
+        pub fn run<H>(&mut self, mut handler: H) -> io::Result<()>
+        where
+            H: FnMut(Msg<F>, SocketAddr) -> Option<Msg<F>>,
+        {
+            loop {
+                let (msg, source) = match self.recv_from() {
+                    Ok(received) => received,
+                    // some platforms report read timeouts as WouldBlock, so only a socket with a
+                    // read timeout may have waited
+                    Err(err)
+                        if (err.kind() == io::ErrorKind::WouldBlock
+                            || err.kind() == io::ErrorKind::TimedOut)
+                            && self.socket.read_timeout()?.is_some() =>
+                    {
+                        continue
+                    }
+                    Err(err) => return Err(err),
+                };
+
+                if let Some(reply) = handler(msg, source) {
+                    self.send_to(reply, source)?;
+                }
+            }
+        }
+    }
+
+    impl<F: FrameFormat> fmt::Debug for UdpTransport<F> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_struct("UdpTransport")
+                .field("socket", &self.socket)
+                .field("encoder", &self.encoder)
+                .finish_non_exhaustive()
+        }
+    }
+}
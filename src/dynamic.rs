@@ -0,0 +1,626 @@
+//! Frame codec with a layout chosen at runtime.
+//!
+//! [Msg::encode](crate::Msg::encode) and [MsgDecoder](crate::MsgDecoder) fix the field widths at
+//! compile time. This module reads and writes the same wire format, but takes the layout from a
+//! [FrameConfig] value so that tooling can handle any device family without being recompiled.
+//! All field values are represented as `u64`.
+
+use crate::checksum::{Checksum, Crc16Sum, Crc32Sum, XorSum};
+use crate::io::{self, Write};
+use alloc::vec::Vec;
+use core::{fmt, mem, str};
+
+/// A checksum type selected at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumKind {
+    /// No checksum (see [NoCheck](crate::NoCheck)).
+    None,
+
+    /// Inverted xor of all bytes (see [XorSum]).
+    Xor,
+
+    /// CRC16 (see [Crc16Sum]).
+    Crc16,
+
+    /// CRC32 (see [Crc32Sum]).
+    Crc32,
+}
+
+impl ChecksumKind {
+    /// Returns the size of the checksum in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            ChecksumKind::None => 0,
+            ChecksumKind::Xor => 1,
+            ChecksumKind::Crc16 => 2,
+            ChecksumKind::Crc32 => 4,
+        }
+    }
+
+    /// Calculates the checksum of the given buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::{Checksum, Crc16Sum};
+    /// # use tiny_frame::dynamic::ChecksumKind;
+    /// let buffer = b"hello world!";
+    /// assert_eq!(ChecksumKind::Crc16.sum(buffer), Crc16Sum::sum(buffer) as u64);
+    /// ```
+    pub fn sum(self, buffer: &[u8]) -> u64 {
+        match self {
+            ChecksumKind::None => 0,
+            ChecksumKind::Xor => XorSum::sum(buffer) as u64,
+            ChecksumKind::Crc16 => Crc16Sum::sum(buffer) as u64,
+            ChecksumKind::Crc32 => Crc32Sum::sum(buffer) as u64,
+        }
+    }
+}
+
+impl str::FromStr for ChecksumKind {
+    type Err = ParseChecksumKindError;
+
+    /// Parses a checksum name: `none`, `xor`, `crc16` or `crc32`, ignoring case.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::dynamic::ChecksumKind;
+    /// assert_eq!("crc16".parse(), Ok(ChecksumKind::Crc16));
+    /// assert!("md5".parse::<ChecksumKind>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<ChecksumKind, ParseChecksumKindError> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(ChecksumKind::None),
+            "xor" => Ok(ChecksumKind::Xor),
+            "crc16" => Ok(ChecksumKind::Crc16),
+            "crc32" => Ok(ChecksumKind::Crc32),
+            _ => Err(ParseChecksumKindError),
+        }
+    }
+}
+
+/// The error returned when parsing an unknown [ChecksumKind] name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseChecksumKindError;
+
+impl fmt::Display for ParseChecksumKindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown checksum (expected none, xor, crc16 or crc32)")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseChecksumKindError {}
+
+/// A frame layout.
+///
+/// Field widths are given in bytes and may be anywhere from 0 (field omitted) to 8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameConfig {
+    /// The start-of-frame byte, if frames start with one.
+    pub sof: Option<u8>,
+
+    /// Width of the ID field.
+    pub id_bytes: usize,
+
+    /// Width of the length field.
+    pub len_bytes: usize,
+
+    /// Width of the type field.
+    pub type_bytes: usize,
+
+    /// The checksum used for the frame head and body.
+    pub checksum: ChecksumKind,
+}
+
+impl FrameConfig {
+    /// Returns true if all field widths are in range and frames have a non-empty head.
+    pub fn is_valid(&self) -> bool {
+        self.id_bytes <= 8 && self.len_bytes <= 8 && self.type_bytes <= 8 && self.head_size() > 0
+    }
+
+    /// Returns the size of the frame head, including the head checksum.
+    pub fn head_size(&self) -> usize {
+        self.sof.map_or(0, |_| 1)
+            + self.id_bytes
+            + self.len_bytes
+            + self.type_bytes
+            + self.checksum.size()
+    }
+
+    /// Returns the largest payload length the length field can hold.
+    pub fn max_len(&self) -> u64 {
+        field_mask(self.len_bytes)
+    }
+
+    /// Returns the master peer bit of the ID field.
+    pub fn master_bit(&self) -> u64 {
+        field_mask(self.id_bytes) ^ (field_mask(self.id_bytes) >> 1)
+    }
+
+    fn assert_valid(&self) {
+        assert!(self.is_valid(), "invalid frame config: {:?}", self);
+    }
+}
+
+/// Returns a mask for a field of the given width in bytes.
+fn field_mask(bytes: usize) -> u64 {
+    match bytes {
+        0 => 0,
+        8 => u64::MAX,
+        _ => (1 << (bytes * 8)) - 1,
+    }
+}
+
+fn write_field(buf: &mut Vec<u8>, value: u64, bytes: usize) {
+    buf.extend_from_slice(&value.to_be_bytes()[8 - bytes..]);
+}
+
+/// A TinyFrame message with runtime-sized fields.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DynMsg {
+    /// The message ID.
+    pub id: u64,
+
+    /// Whether or not this message is a response.
+    pub is_response: bool,
+
+    /// The message type.
+    pub msg_type: u64,
+
+    /// The message data.
+    pub data: Vec<u8>,
+}
+
+/// A TinyFrame message encoder for a runtime frame layout.
+///
+/// This is the counterpart of [MsgEncoder](crate::MsgEncoder).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DynMsgEncoder {
+    config: FrameConfig,
+    next_id: u64,
+
+    /// Should be set to true if this is the master peer.
+    pub is_master: bool,
+}
+
+impl DynMsgEncoder {
+    /// Creates a new DynMsgEncoder.
+    ///
+    /// # Panics
+    /// Panics if the config is not [valid](FrameConfig::is_valid).
+    pub fn new(config: FrameConfig) -> DynMsgEncoder {
+        config.assert_valid();
+
+        DynMsgEncoder {
+            config,
+            next_id: 0,
+            is_master: false,
+        }
+    }
+
+    /// Returns the frame layout.
+    pub fn config(&self) -> &FrameConfig {
+        &self.config
+    }
+
+    /// Returns the next message ID.
+    pub fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id = id.wrapping_add(1) & (field_mask(self.config.id_bytes) >> 1);
+        if self.is_master {
+            id | self.config.master_bit()
+        } else {
+            id
+        }
+    }
+
+    /// Resets the ID counter.
+    pub fn reset(&mut self) {
+        self.next_id = 0;
+    }
+}
+
+impl DynMsg {
+    /// Encodes this message into the given [Write] implementor with the given encoder.
+    /// If this message is not a response, a new ID will be assigned by the encoder.
+    ///
+    /// Field values that do not fit into their configured width are truncated.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::*;
+    /// # use tiny_frame::dynamic::*;
+    /// let config = FrameConfig {
+    ///     sof: Some(1),
+    ///     id_bytes: 1,
+    ///     len_bytes: 2,
+    ///     type_bytes: 1,
+    ///     checksum: ChecksumKind::Crc16,
+    /// };
+    /// let msg = DynMsg {
+    ///     id: 0,
+    ///     is_response: false,
+    ///     msg_type: 3,
+    ///     data: b"hello world!".to_vec(),
+    /// };
+    /// let mut bytes = Vec::new();
+    /// msg.encode(&mut bytes, &mut DynMsgEncoder::new(config)).expect("Failed to encode");
+    ///
+    /// // the generic encoder produces the same frame
+    /// let msg: Msg<DefaultFormat> = Msg {
+    ///     id: 0,
+    ///     is_response: false,
+    ///     msg_type: 3,
+    ///     data: b"hello world!".to_vec(),
+    /// };
+    /// let mut expected = Vec::new();
+    /// msg.encode(&mut expected, &mut MsgEncoder::new()).expect("Failed to encode");
+    ///
+    /// assert_eq!(bytes, expected);
+    /// ```
+    pub fn encode<W: Write>(mut self, out: &mut W, encoder: &mut DynMsgEncoder) -> io::Result<()> {
+        let config = encoder.config;
+
+        if !self.is_response {
+            self.id = encoder.next_id();
+        }
+
+        if self.data.len() as u64 > config.max_len() {
+            return Err(io::Error::other("Length field cannot hold message length"));
+        }
+
+        let mut buf = Vec::with_capacity(config.head_size() + self.data.len() + 4);
+
+        if let Some(sof_byte) = config.sof {
+            buf.push(sof_byte);
+        }
+
+        write_field(&mut buf, self.id, config.id_bytes);
+        write_field(&mut buf, self.data.len() as u64, config.len_bytes);
+        write_field(&mut buf, self.msg_type, config.type_bytes);
+
+        let cksum = config.checksum.sum(&buf);
+        write_field(&mut buf, cksum, config.checksum.size());
+
+        // frames without a body have no data checksum
+        if !self.data.is_empty() {
+            buf.extend_from_slice(&self.data);
+            let cksum = config.checksum.sum(&self.data);
+            write_field(&mut buf, cksum, config.checksum.size());
+        }
+
+        out.write_all(&buf)
+    }
+
+    /// Creates a response message to this message.
+    pub fn create_response(&self, ty: u64, data: Vec<u8>) -> DynMsg {
+        DynMsg {
+            id: self.id,
+            is_response: true,
+            msg_type: ty,
+            data,
+        }
+    }
+}
+
+/// Errors reported by [DynMsgDecoder::try_accept].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DecodeError {
+    /// The head checksum did not match. The rest of the frame has been skipped.
+    HeadChecksum {
+        /// The checksum calculated from the received head.
+        expected: u64,
+        /// The checksum that was received.
+        received: u64,
+    },
+
+    /// The data checksum did not match.
+    DataChecksum {
+        /// The received message.
+        msg: DynMsg,
+        /// The checksum calculated from the received data.
+        expected: u64,
+        /// The checksum that was received.
+        received: u64,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::HeadChecksum { expected, received } => write!(
+                f,
+                "head checksum mismatch (expected {:#x}, received {:#x})",
+                expected, received
+            ),
+            DecodeError::DataChecksum {
+                expected, received, ..
+            } => write!(
+                f,
+                "data checksum mismatch (expected {:#x}, received {:#x})",
+                expected, received
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// Parser states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ParserState {
+    Sof,
+    ID,
+    Len,
+    Type,
+    HeadCksum,
+    Data,
+    DataCksum,
+}
+
+/// A TinyFrame message decoder for a runtime frame layout.
+///
+/// This is the counterpart of [MsgDecoder](crate::MsgDecoder).
+#[derive(Debug, Clone)]
+pub struct DynMsgDecoder {
+    config: FrameConfig,
+    state: ParserState,
+    part_len: usize,
+    field: u64,
+    id: u64,
+    len: u64,
+    ty: u64,
+    data: Vec<u8>,
+}
+
+impl DynMsgDecoder {
+    /// Creates a new DynMsgDecoder.
+    ///
+    /// # Panics
+    /// Panics if the config is not [valid](FrameConfig::is_valid).
+    pub fn new(config: FrameConfig) -> DynMsgDecoder {
+        config.assert_valid();
+
+        DynMsgDecoder {
+            config,
+            state: ParserState::Sof,
+            part_len: 0,
+            field: 0,
+            id: 0,
+            len: 0,
+            ty: 0,
+            data: Vec::new(),
+        }
+    }
+
+    /// Returns the frame layout.
+    pub fn config(&self) -> &FrameConfig {
+        &self.config
+    }
+
+    /// Resets this DynMsgDecoder to initial state.
+    pub fn reset(&mut self) {
+        self.state = ParserState::Sof;
+        self.part_len = 0;
+        self.field = 0;
+        self.id = 0;
+        self.len = 0;
+        self.ty = 0;
+        self.data = Vec::new();
+    }
+
+    fn field_size(&self, state: ParserState) -> u64 {
+        match state {
+            ParserState::Sof => 0,
+            ParserState::ID => self.config.id_bytes as u64,
+            ParserState::Len => self.config.len_bytes as u64,
+            ParserState::Type => self.config.type_bytes as u64,
+            ParserState::HeadCksum | ParserState::DataCksum => self.config.checksum.size() as u64,
+            ParserState::Data => self.len,
+        }
+    }
+
+    fn received_msg(&mut self) -> DynMsg {
+        DynMsg {
+            id: self.id,
+            is_response: false,
+            msg_type: self.ty,
+            data: mem::take(&mut self.data),
+        }
+    }
+
+    /// Moves on to the next field with a non-zero size, and finishes the frame if there is none.
+    fn advance(&mut self) -> Option<Result<DynMsg, DecodeError>> {
+        loop {
+            let received = mem::replace(&mut self.field, 0);
+            self.part_len = 0;
+
+            self.state = match self.state {
+                ParserState::Sof => ParserState::ID,
+                ParserState::ID => {
+                    self.id = received;
+                    ParserState::Len
+                }
+                ParserState::Len => {
+                    self.len = received;
+                    ParserState::Type
+                }
+                ParserState::Type => {
+                    self.ty = received;
+                    ParserState::HeadCksum
+                }
+                ParserState::HeadCksum => {
+                    let expected = self.config.checksum.sum(&self.data);
+                    self.data = Vec::new();
+
+                    if expected != received {
+                        self.reset();
+                        return Some(Err(DecodeError::HeadChecksum { expected, received }));
+                    }
+
+                    if self.len == 0 {
+                        let msg = self.received_msg();
+                        self.reset();
+                        return Some(Ok(msg));
+                    }
+
+                    ParserState::Data
+                }
+                ParserState::Data => ParserState::DataCksum,
+                ParserState::DataCksum => {
+                    let expected = self.config.checksum.sum(&self.data);
+                    let msg = self.received_msg();
+                    self.reset();
+
+                    return Some(if expected == received {
+                        Ok(msg)
+                    } else {
+                        Err(DecodeError::DataChecksum {
+                            msg,
+                            expected,
+                            received,
+                        })
+                    });
+                }
+            };
+
+            if self.field_size(self.state) > 0 {
+                return None;
+            }
+        }
+    }
+
+    /// Accepts a single byte. Will return the received message if the frame has ended.
+    ///
+    /// Frames with checksum errors are dropped silently; use [DynMsgDecoder::try_accept] to
+    /// find out about them.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::*;
+    /// # use tiny_frame::dynamic::*;
+    /// // encode a message with the generic encoder
+    /// enum MyFormat {}
+    ///
+    /// impl FrameFormat for MyFormat {
+    ///     type Id = u16;
+    ///     type Len = u8;
+    ///     type Type = u8;
+    ///     type Checksum = XorSum;
+    ///     const SOF: Option<u8> = None;
+    /// }
+    ///
+    /// let msg: Msg<MyFormat> = Msg {
+    ///     id: 0,
+    ///     is_response: false,
+    ///     msg_type: 7,
+    ///     data: b"hello world!".to_vec(),
+    /// };
+    /// let mut bytes = Vec::new();
+    /// msg.encode(&mut bytes, &mut MsgEncoder::new()).expect("Failed to encode");
+    ///
+    /// // and decode it with a matching layout
+    /// let mut decoder = DynMsgDecoder::new(FrameConfig {
+    ///     sof: None,
+    ///     id_bytes: 2,
+    ///     len_bytes: 1,
+    ///     type_bytes: 1,
+    ///     checksum: ChecksumKind::Xor,
+    /// });
+    /// let received = bytes.into_iter().find_map(|byte| decoder.accept(byte));
+    ///
+    /// assert_eq!(
+    ///     received,
+    ///     Some(DynMsg {
+    ///         id: 0,
+    ///         is_response: false,
+    ///         msg_type: 7,
+    ///         data: b"hello world!".to_vec(),
+    ///     })
+    /// );
+    /// ```
+    pub fn accept(&mut self, byte: u8) -> Option<DynMsg> {
+        match self.try_accept(byte) {
+            Some(Ok(msg)) => Some(msg),
+            _ => None,
+        }
+    }
+
+    /// Accepts a single byte. Will return the received message or a checksum error if the frame
+    /// has ended.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::dynamic::*;
+    /// let config = FrameConfig {
+    ///     sof: Some(0x01),
+    ///     id_bytes: 1,
+    ///     len_bytes: 1,
+    ///     type_bytes: 1,
+    ///     checksum: ChecksumKind::Crc32,
+    /// };
+    /// let msg = DynMsg {
+    ///     id: 0,
+    ///     is_response: false,
+    ///     msg_type: 1,
+    ///     data: vec![1, 2, 3],
+    /// };
+    /// let mut bytes = Vec::new();
+    /// msg.encode(&mut bytes, &mut DynMsgEncoder::new(config)).expect("Failed to encode");
+    ///
+    /// // corrupt the payload
+    /// bytes[8] ^= 0xff;
+    ///
+    /// let mut decoder = DynMsgDecoder::new(config);
+    /// match bytes.into_iter().find_map(|byte| decoder.try_accept(byte)) {
+    ///     Some(Err(DecodeError::DataChecksum { msg, .. })) => assert_eq!(msg.data, [0xfe, 2, 3]),
+    ///     other => panic!("unexpected result {:?}", other),
+    /// }
+    /// ```
+    pub fn try_accept(&mut self, byte: u8) -> Option<Result<DynMsg, DecodeError>> {
+        if self.state == ParserState::Sof {
+            match self.config.sof {
+                Some(sof_byte) => {
+                    if byte == sof_byte {
+                        self.reset();
+                        self.data.push(byte);
+                        return self.advance();
+                    }
+                    return None;
+                }
+                None => {
+                    self.reset();
+                    if let Some(result) = self.advance() {
+                        return Some(result);
+                    }
+                }
+            }
+        }
+
+        match self.state {
+            ParserState::Sof => unreachable!(),
+            ParserState::ID | ParserState::Len | ParserState::Type => self.data.push(byte),
+            ParserState::HeadCksum | ParserState::DataCksum => (),
+            ParserState::Data => {
+                self.data.push(byte);
+                self.part_len += 1;
+
+                if self.part_len as u64 == self.len {
+                    return self.advance();
+                }
+                return None;
+            }
+        }
+
+        self.field = (self.field << 8) | byte as u64;
+        self.part_len += 1;
+
+        if self.part_len as u64 == self.field_size(self.state) {
+            return self.advance();
+        }
+
+        None
+    }
+}
@@ -0,0 +1,254 @@
+//! Reading and writing frames over `embedded-io`.
+//!
+//! This module is only available with the `embedded-io` feature, and does not need `std`.
+//! [FrameReader] and [FrameWriter] wrap a device implementing the [embedded_io] traits, such as
+//! a UART, and provide blocking methods as well as async methods for devices implementing the
+//! [embedded_io_async] traits.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use tiny_frame::embedded::{FrameReader, FrameWriter};
+//!
+//! let mut buf = [0; 64];
+//! let mut writer = FrameWriter::<DefaultFormat, _>::new(MsgEncoder::new(), &mut buf[..]);
+//! let msg = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 0x20,
+//!     data: b"hello".to_vec(),
+//! };
+//! writer.write_msg(msg).unwrap();
+//! let len = 64 - writer.into_inner().len();
+//!
+//! let mut reader = FrameReader::<DefaultFormat, _>::new(MsgDecoder::new(), &buf[..len]);
+//! assert_eq!(reader.read_msg().unwrap().data, b"hello");
+//!
+//! // the async methods work the same way
+//! futures_executor::block_on(async {
+//!     let mut copy = [0; 64];
+//!     let mut writer = FrameWriter::<DefaultFormat, _>::new(MsgEncoder::new(), &mut copy[..]);
+//!     let msg = Msg {
+//!         id: 0,
+//!         is_response: false,
+//!         msg_type: 0x20,
+//!         data: b"hello".to_vec(),
+//!     };
+//!     writer.write_msg_async(msg).await.unwrap();
+//!     assert_eq!(copy[..len], buf[..len]);
+//!
+//!     let mut reader = FrameReader::<DefaultFormat, _>::new(MsgDecoder::new(), &buf[..len]);
+//!     assert_eq!(reader.read_msg_async().await.unwrap().data, b"hello");
+//! });
+//! ```
+
+use crate::{FrameFormat, Msg, MsgDecoder, MsgEncoder};
+use alloc::vec::Vec;
+use core::fmt;
+
+const READ_BUFFER_SIZE: usize = 64;
+
+/// Errors returned when reading or writing frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Error<E> {
+    /// The device returned an error.
+    Io(E),
+
+    /// The device reached the end of its input.
+    Eof,
+
+    /// The message is too long for the length field.
+    TooLong,
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "i/o error: {:?}", err),
+            Error::Eof => write!(f, "unexpected end of input"),
+            Error::TooLong => write!(f, "message is too long for the length field"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for Error<E> {}
+
+impl<E: embedded_io::Error> embedded_io::Error for Error<E> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Error::Io(err) => err.kind(),
+            Error::Eof => embedded_io::ErrorKind::Other,
+            Error::TooLong => embedded_io::ErrorKind::InvalidInput,
+        }
+    }
+}
+
+/// Reads messages from a device.
+///
+/// Frames that fail to decode are dropped, as with [MsgDecoder::accept].
+pub struct FrameReader<F: FrameFormat, R> {
+    decoder: MsgDecoder<F>,
+    reader: R,
+    buf: [u8; READ_BUFFER_SIZE],
+    pos: usize,
+    len: usize,
+}
+
+impl<F: FrameFormat, R> FrameReader<F, R> {
+    /// Creates a new reader.
+    pub fn new(decoder: MsgDecoder<F>, reader: R) -> FrameReader<F, R> {
+        FrameReader {
+            decoder,
+            reader,
+            buf: [0; READ_BUFFER_SIZE],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the decoder.
+    pub fn decoder(&mut self) -> &mut MsgDecoder<F> {
+        &mut self.decoder
+    }
+
+    /// Returns the device. Reading from it directly will corrupt the stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the device, discarding buffered input.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Passes buffered bytes to the decoder until a message is complete.
+    fn decode_buffered(&mut self) -> Option<Msg<F>> {
+        while self.pos < self.len {
+            let byte = self.buf[self.pos];
+            self.pos += 1;
+            if let Some(msg) = self.decoder.accept(byte) {
+                return Some(msg);
+            }
+        }
+        None
+    }
+
+    fn refill<E>(&mut self, read: Result<usize, E>) -> Result<(), Error<E>> {
+        match read {
+            Ok(0) => Err(Error::Eof),
+            Ok(len) => {
+                self.pos = 0;
+                self.len = len;
+                Ok(())
+            }
+            Err(err) => Err(Error::Io(err)),
+        }
+    }
+}
+
+impl<F: FrameFormat, R: embedded_io::Read> FrameReader<F, R> {
+    /// Reads the next message, blocking until it is complete.
+    pub fn read_msg(&mut self) -> Result<Msg<F>, Error<R::Error>> {
+        loop {
+            if let Some(msg) = self.decode_buffered() {
+                return Ok(msg);
+            }
+            let read = self.reader.read(&mut self.buf);
+            self.refill(read)?;
+        }
+    }
+}
+
+impl<F: FrameFormat, R: embedded_io_async::Read> FrameReader<F, R> {
+    /// Reads the next message.
+    pub async fn read_msg_async(&mut self) -> Result<Msg<F>, Error<R::Error>> {
+        loop {
+            if let Some(msg) = self.decode_buffered() {
+                return Ok(msg);
+            }
+            let read = self.reader.read(&mut self.buf).await;
+            self.refill(read)?;
+        }
+    }
+}
+
+impl<F: FrameFormat, R: fmt::Debug> fmt::Debug for FrameReader<F, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FrameReader")
+            .field("reader", &self.reader)
+            .field("buffered", &(self.len - self.pos))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Writes messages to a device.
+pub struct FrameWriter<F: FrameFormat, W> {
+    encoder: MsgEncoder<F>,
+    writer: W,
+    buf: Vec<u8>,
+}
+
+impl<F: FrameFormat, W> FrameWriter<F, W> {
+    /// Creates a new writer.
+    pub fn new(encoder: MsgEncoder<F>, writer: W) -> FrameWriter<F, W> {
+        FrameWriter {
+            encoder,
+            writer,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Returns the encoder.
+    pub fn encoder(&mut self) -> &mut MsgEncoder<F> {
+        &mut self.encoder
+    }
+
+    /// Returns the device.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the device.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Encodes a message into the buffer.
+    fn encode<E>(&mut self, msg: Msg<F>) -> Result<F::Id, Error<E>> {
+        self.buf.clear();
+        msg.encode(&mut self.buf, &mut self.encoder)
+            .map_err(|_| Error::TooLong)
+    }
+}
+
+impl<F: FrameFormat, W: embedded_io::Write> FrameWriter<F, W> {
+    /// Writes a message and flushes the device. If the message is not a response, a new ID
+    /// will be assigned. Returns the ID of the message.
+    pub fn write_msg(&mut self, msg: Msg<F>) -> Result<F::Id, Error<W::Error>> {
+        let id = self.encode(msg)?;
+        self.writer.write_all(&self.buf).map_err(Error::Io)?;
+        self.writer.flush().map_err(Error::Io)?;
+        Ok(id)
+    }
+}
+
+impl<F: FrameFormat, W: embedded_io_async::Write> FrameWriter<F, W> {
+    /// Writes a message and flushes the device. If the message is not a response, a new ID
+    /// will be assigned. Returns the ID of the message.
+    pub async fn write_msg_async(&mut self, msg: Msg<F>) -> Result<F::Id, Error<W::Error>> {
+        let id = self.encode(msg)?;
+        self.writer.write_all(&self.buf).await.map_err(Error::Io)?;
+        self.writer.flush().await.map_err(Error::Io)?;
+        Ok(id)
+    }
+}
+
+impl<F: FrameFormat, W: fmt::Debug> fmt::Debug for FrameWriter<F, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FrameWriter")
+            .field("encoder", &self.encoder)
+            .field("writer", &self.writer)
+            .finish()
+    }
+}
@@ -0,0 +1,347 @@
+//! Fragmentation of payloads that do not fit into a single frame.
+//!
+//! [split] turns a message into a number of fragment messages that are small enough to be
+//! encoded, and [Reassembler] joins them back together on the receiving side.
+//!
+//! All fragments of a message have the ID and message type of the original message, and are
+//! sent as responses so that the encoder does not assign new IDs to them. Every fragment payload
+//! starts with a header of [HEADER_SIZE] bytes: the big endian fragment index as a `u16`,
+//! followed by a flags byte, which has [LAST_FRAGMENT] set on the last fragment. Fragments must
+//! arrive in order.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use tiny_frame::fragment::{self, Reassembler};
+//!
+//! enum SmallFormat {}
+//!
+//! impl FrameFormat for SmallFormat {
+//!     type Id = u8;
+//!     type Len = u8;
+//!     type Type = u8;
+//!     type Checksum = Crc16Sum;
+//!     const SOF: Option<u8> = Some(0x01);
+//! }
+//!
+//! let msg: Msg<SmallFormat> = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 0x30,
+//!     data: vec![7; 1000],
+//! };
+//!
+//! let mut encoder = MsgEncoder::new();
+//! let mut wire = Vec::new();
+//! let id = fragment::encode_fragmented(msg, &mut wire, &mut encoder, 255).unwrap();
+//!
+//! let mut decoder = MsgDecoder::<SmallFormat>::new();
+//! let mut reassembler = Reassembler::new(100, 4096, 4096);
+//! let mut received = Vec::new();
+//! for byte in wire {
+//!     if let Some(fragment) = decoder.accept(byte) {
+//!         if let Some(msg) = reassembler.accept(fragment, 0).unwrap() {
+//!             received.push(msg);
+//!         }
+//!     }
+//! }
+//!
+//! assert_eq!(received.len(), 1);
+//! assert_eq!(received[0].id, id);
+//! assert_eq!(received[0].data, vec![7; 1000]);
+//! ```
+
+use crate::number::FrameLen;
+use crate::{FrameFormat, Msg, MsgEncoder};
+use std::fmt;
+use std::io::{self, Write};
+
+/// Size of the fragment header at the start of every fragment payload.
+pub const HEADER_SIZE: usize = 3;
+
+/// Flag set on the last fragment of a message.
+pub const LAST_FRAGMENT: u8 = 0x01;
+
+/// Errors returned when fragmenting or reassembling messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FragmentError {
+    /// The maximum fragment payload size cannot hold the fragment header and any data.
+    MaxPayloadTooSmall,
+
+    /// The maximum fragment payload size does not fit into the length field of the frame format.
+    MaxPayloadTooLarge,
+
+    /// The message needs more fragments than the fragment index can count.
+    TooManyFragments,
+
+    /// A received fragment is shorter than the fragment header.
+    Truncated,
+
+    /// A received fragment does not follow the previous fragment of its message. The partially
+    /// received message was discarded.
+    OutOfOrder,
+
+    /// A received message exceeds the maximum message size. The partially received message was
+    /// discarded.
+    TooLarge,
+
+    /// Buffering a received fragment would exceed the memory limit. The partially received
+    /// message was discarded.
+    OutOfMemory,
+}
+
+impl fmt::Display for FragmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FragmentError::MaxPayloadTooSmall => write!(f, "maximum payload size is too small"),
+            FragmentError::MaxPayloadTooLarge => write!(f, "maximum payload size is too large"),
+            FragmentError::TooManyFragments => write!(f, "message needs too many fragments"),
+            FragmentError::Truncated => write!(f, "fragment is missing its header"),
+            FragmentError::OutOfOrder => write!(f, "fragment received out of order"),
+            FragmentError::TooLarge => write!(f, "reassembled message is too large"),
+            FragmentError::OutOfMemory => write!(f, "reassembly memory limit exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for FragmentError {}
+
+impl From<FragmentError> for io::Error {
+    fn from(err: FragmentError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+/// Splits a message into fragments with payloads of at most `max_payload` bytes, including the
+/// fragment header. If the message is not a response, a new ID will be assigned by the encoder.
+///
+/// Messages that are empty still produce a single fragment.
+pub fn split<F: FrameFormat>(
+    mut msg: Msg<F>,
+    encoder: &mut MsgEncoder<F>,
+    max_payload: usize,
+) -> Result<Vec<Msg<F>>, FragmentError> {
+    if max_payload <= HEADER_SIZE {
+        return Err(FragmentError::MaxPayloadTooSmall);
+    }
+    if F::Len::from_usize(max_payload).is_none() {
+        return Err(FragmentError::MaxPayloadTooLarge);
+    }
+
+    let chunk_size = max_payload - HEADER_SIZE;
+    let count = msg.data.len().div_ceil(chunk_size).max(1);
+    if count > u16::MAX as usize + 1 {
+        return Err(FragmentError::TooManyFragments);
+    }
+
+    if !msg.is_response {
+        msg.id = encoder.next_id();
+    }
+
+    let mut chunks = msg.data.chunks(chunk_size);
+    let fragments = (0..count)
+        .map(|index| {
+            let chunk = chunks.next().unwrap_or(&[]);
+            let flags = if index + 1 == count { LAST_FRAGMENT } else { 0 };
+
+            let mut data = Vec::with_capacity(HEADER_SIZE + chunk.len());
+            data.extend_from_slice(&(index as u16).to_be_bytes());
+            data.push(flags);
+            data.extend_from_slice(chunk);
+
+            Msg {
+                id: msg.id,
+                is_response: true,
+                msg_type: msg.msg_type,
+                data,
+            }
+        })
+        .collect();
+
+    Ok(fragments)
+}
+
+/// Splits a message into fragments (see [split]) and encodes all of them into the given output.
+/// Returns the ID of the message. Nothing is written if the message cannot be fragmented.
+///
+/// # Examples
+/// ```
+/// # use tiny_frame::*;
+/// use tiny_frame::fragment::{self, FragmentError};
+///
+/// enum SmallFormat {}
+///
+/// impl FrameFormat for SmallFormat {
+///     type Id = u8;
+///     type Len = u8;
+///     type Type = u8;
+///     type Checksum = Crc16Sum;
+///     const SOF: Option<u8> = Some(0x01);
+/// }
+///
+/// let msg: Msg<SmallFormat> = Msg {
+///     id: 0,
+///     is_response: false,
+///     msg_type: 0x30,
+///     data: vec![7; 1000],
+/// };
+///
+/// // a u8 length field cannot describe 256 byte payloads
+/// let mut wire = Vec::new();
+/// let result = fragment::encode_fragmented(msg, &mut wire, &mut MsgEncoder::new(), 256);
+/// assert_eq!(
+///     result.unwrap_err().into_inner().unwrap().downcast_ref(),
+///     Some(&FragmentError::MaxPayloadTooLarge)
+/// );
+/// assert!(wire.is_empty());
+/// ```
+pub fn encode_fragmented<F: FrameFormat, W: Write>(
+    msg: Msg<F>,
+    out: &mut W,
+    encoder: &mut MsgEncoder<F>,
+    max_payload: usize,
+) -> io::Result<F::Id> {
+    let mut id = msg.id;
+    for fragment in split(msg, encoder, max_payload)? {
+        id = fragment.encode(out, encoder)?;
+    }
+    Ok(id)
+}
+
+struct Partial<F: FrameFormat> {
+    id: F::Id,
+    msg_type: F::Type,
+    next_index: u32,
+    data: Vec<u8>,
+    deadline: u64,
+}
+
+/// Joins fragments back into messages.
+///
+/// Partially received messages are discarded if no fragment arrives for `timeout` ticks, if
+/// they exceed `max_size` bytes, or if the data of all partially received messages would exceed
+/// `max_buffered` bytes. Like upstream TinyFrame, this does not read a clock: the current time is
+/// passed to [accept](Self::accept) and [tick](Self::tick) in ticks of any unit.
+pub struct Reassembler<F: FrameFormat> {
+    partial: Vec<Partial<F>>,
+    timeout: u64,
+    max_size: usize,
+    max_buffered: usize,
+}
+
+impl<F: FrameFormat> Reassembler<F> {
+    /// Creates a new reassembler.
+    pub fn new(timeout: u64, max_size: usize, max_buffered: usize) -> Reassembler<F> {
+        Reassembler {
+            partial: Vec::new(),
+            timeout,
+            max_size,
+            max_buffered,
+        }
+    }
+
+    /// Returns the number of bytes buffered for partially received messages.
+    pub fn buffered(&self) -> usize {
+        self.partial.iter().map(|partial| partial.data.len()).sum()
+    }
+
+    /// Discards all partially received messages.
+    pub fn reset(&mut self) {
+        self.partial.clear();
+    }
+
+    /// Accepts a received fragment. Returns the reassembled message once its last fragment has
+    /// been received.
+    pub fn accept(&mut self, msg: Msg<F>, now: u64) -> Result<Option<Msg<F>>, FragmentError> {
+        self.tick(now);
+
+        if msg.data.len() < HEADER_SIZE {
+            return Err(FragmentError::Truncated);
+        }
+        let index = u16::from_be_bytes([msg.data[0], msg.data[1]]) as u32;
+        let flags = msg.data[2];
+        let chunk = &msg.data[HEADER_SIZE..];
+
+        let existing = self
+            .partial
+            .iter()
+            .position(|partial| partial.id == msg.id && partial.msg_type == msg.msg_type);
+
+        let i = match existing {
+            // a new first fragment replaces an incomplete message with the same ID
+            Some(i) if index == 0 => {
+                self.partial.remove(i);
+                None
+            }
+            Some(i) if self.partial[i].next_index != index => {
+                self.partial.remove(i);
+                return Err(FragmentError::OutOfOrder);
+            }
+            Some(i) => Some(i),
+            None if index != 0 => return Err(FragmentError::OutOfOrder),
+            None => None,
+        };
+
+        let size = i.map_or(0, |i| self.partial[i].data.len()) + chunk.len();
+        let error = if size > self.max_size {
+            Some(FragmentError::TooLarge)
+        } else if self.buffered() + chunk.len() > self.max_buffered {
+            Some(FragmentError::OutOfMemory)
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            if let Some(i) = i {
+                self.partial.remove(i);
+            }
+            return Err(error);
+        }
+
+        let i = match i {
+            Some(i) => i,
+            None => {
+                self.partial.push(Partial {
+                    id: msg.id,
+                    msg_type: msg.msg_type,
+                    next_index: 0,
+                    data: Vec::new(),
+                    deadline: 0,
+                });
+                self.partial.len() - 1
+            }
+        };
+
+        let partial = &mut self.partial[i];
+        partial.data.extend_from_slice(chunk);
+        partial.next_index = index + 1;
+        partial.deadline = now.saturating_add(self.timeout);
+
+        if flags & LAST_FRAGMENT == 0 {
+            return Ok(None);
+        }
+
+        let partial = self.partial.remove(i);
+        Ok(Some(Msg {
+            id: partial.id,
+            is_response: msg.is_response,
+            msg_type: partial.msg_type,
+            data: partial.data,
+        }))
+    }
+
+    /// Discards partially received messages that have timed out.
+    pub fn tick(&mut self, now: u64) {
+        self.partial.retain(|partial| partial.deadline > now);
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for Reassembler<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Reassembler")
+            .field("partial", &self.partial.len())
+            .field("timeout", &self.timeout)
+            .field("max_size", &self.max_size)
+            .field("max_buffered", &self.max_buffered)
+            .finish()
+    }
+}
@@ -0,0 +1,58 @@
+//! I/O types used by the encoders.
+//!
+//! With the `std` feature, which is enabled by default, these are the types from `std::io`.
+//! Without it, this module provides a minimal replacement that is just enough to encode frames
+//! into a `Vec<u8>`; to write frames to a device, see the `embedded` module.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, Result, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::no_std::{Error, Result, Write};
+
+#[cfg(not(feature = "std"))]
+mod no_std {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    /// An error returned when encoding a frame.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Error {
+        msg: &'static str,
+    }
+
+    impl Error {
+        /// Creates an error with the given message.
+        pub fn other(msg: &'static str) -> Error {
+            Error { msg }
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.msg)
+        }
+    }
+
+    /// A result with an [Error].
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// A byte sink.
+    pub trait Write {
+        /// Writes the whole buffer.
+        fn write_all(&mut self, buf: &[u8]) -> Result<()>;
+    }
+
+    impl Write for Vec<u8> {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            self.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            (**self).write_all(buf)
+        }
+    }
+}
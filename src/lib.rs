@@ -1,60 +1,206 @@
-use crate::number::{BufferReadable, BufferWritable, GenericNumber};
-use std::io::{self, Write};
-use std::mem;
-
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use crate::io::Write;
+use crate::number::{BufferReadable, BufferWritable, FrameId, FrameLen, FrameType};
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::mem;
+
+#[cfg(feature = "aead")]
+pub mod aead;
+#[cfg(feature = "std")]
+pub mod auth;
+#[cfg(feature = "capi")]
+pub mod capi;
 pub mod checksum;
+pub mod compress;
+pub mod datagram;
+pub mod dynamic;
+#[cfg(feature = "embedded-io")]
+pub mod embedded;
+#[cfg(feature = "std")]
+pub mod fragment;
+pub mod io;
+pub mod message;
+#[cfg(feature = "std")]
+pub mod net;
+#[cfg(feature = "std")]
+pub mod nonblocking;
 pub mod number;
+pub mod payload;
+#[cfg(feature = "std")]
+pub mod peer;
+#[cfg(feature = "std")]
+pub mod query;
+#[cfg(feature = "std")]
+pub mod reliable;
+#[cfg(feature = "std")]
+pub mod sender;
+#[cfg(feature = "serial")]
+pub mod serial;
+#[cfg(feature = "serde")]
+pub mod serialize;
+#[cfg(feature = "futures")]
+pub mod stream;
 
 pub use self::checksum::*;
+pub use self::message::TinyFrameMessage;
+
+#[cfg(feature = "derive")]
+pub use tiny_frame_derive::{FrameType, TinyFrameMessage};
+
+// used by the derive macros, which cannot assume that `alloc` is in scope
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec::Vec;
+}
+
+/// A frame format.
+///
+/// This bundles all parameters of the frame layout, so that [Msg], [MsgEncoder] and
+/// [MsgDecoder] can be parameterized by a single type and cannot disagree on the wire format.
+///
+/// # Examples
+/// ```
+/// # use tiny_frame::*;
+/// enum MyFormat {}
+///
+/// impl FrameFormat for MyFormat {
+///     type Id = u16;
+///     type Len = u8;
+///     type Type = u8;
+///     type Checksum = XorSum;
+///     const SOF: Option<u8> = None;
+/// }
+///
+/// let mut decoder: MsgDecoder<MyFormat> = MsgDecoder::new();
+/// ```
+pub trait FrameFormat {
+    /// The type of the ID field.
+    type Id: FrameId;
+
+    /// The type of the length field.
+    type Len: FrameLen;
+
+    /// The type of the message type field.
+    type Type: FrameType;
+
+    /// The checksum used for the frame head and body.
+    type Checksum: Checksum;
+
+    /// The start-of-frame byte. If set, every frame will start with this byte.
+    const SOF: Option<u8>;
+}
+
+/// The frame format used by the example configuration of upstream TinyFrame: a `0x01`
+/// start-of-frame byte, 1-byte IDs, 2-byte lengths, 1-byte types and CRC16 checksums.
+pub enum DefaultFormat {}
+
+impl FrameFormat for DefaultFormat {
+    type Id = u8;
+    type Len = u16;
+    type Type = u8;
+    type Checksum = Crc16Sum;
+    const SOF: Option<u8> = Some(0x01);
+}
 
 /// A TinyFrame message.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Msg<ID, Type> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F::Id: serde::Serialize, F::Type: serde::Serialize",
+        deserialize = "F::Id: serde::Deserialize<'de>, F::Type: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Msg<F: FrameFormat> {
     /// The message ID.
-    pub id: ID,
+    pub id: F::Id,
 
     /// Whether or not this message is a response.
     pub is_response: bool,
 
     /// The message type.
-    pub msg_type: Type,
+    pub msg_type: F::Type,
 
     /// The message data.
     pub data: Vec<u8>,
 }
 
+impl<F: FrameFormat> fmt::Debug for Msg<F>
+where
+    F::Type: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Msg")
+            .field("id", &self.id)
+            .field("is_response", &self.is_response)
+            .field("msg_type", &self.msg_type)
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+impl<F: FrameFormat> Clone for Msg<F> {
+    fn clone(&self) -> Msg<F> {
+        Msg {
+            id: self.id,
+            is_response: self.is_response,
+            msg_type: self.msg_type,
+            data: self.data.clone(),
+        }
+    }
+}
+
+impl<F: FrameFormat> PartialEq for Msg<F> {
+    fn eq(&self, other: &Msg<F>) -> bool {
+        self.id == other.id
+            && self.is_response == other.is_response
+            && self.msg_type == other.msg_type
+            && self.data == other.data
+    }
+}
+
+impl<F: FrameFormat> Eq for Msg<F> where F::Type: Eq {}
+
+impl<F: FrameFormat> Hash for Msg<F>
+where
+    F::Type: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.is_response.hash(state);
+        self.msg_type.hash(state);
+        self.data.hash(state);
+    }
+}
+
 /// A TinyFrame message encoder.
 ///
-/// This will keep track of the next message ID, and contains the Start-of-Frame byte and whether
-/// or not this is the master peer.
+/// This will keep track of the next message ID and whether or not this is the master peer.
 ///
-/// See [Message::encode] for actual encoding.
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct MsgEncoder<ID> {
-    next_id: ID,
-
-    /// The start-of-frame byte. If set, will be prepended to every encoded message.
-    pub sof_byte: Option<u8>,
+/// See [Msg::encode] for actual encoding.
+pub struct MsgEncoder<F: FrameFormat> {
+    next_id: F::Id,
 
     /// Should be set to true if this is the master peer.
     pub is_master: bool,
 }
 
-impl<ID> MsgEncoder<ID>
-where
-    ID: GenericNumber,
-{
+impl<F: FrameFormat> MsgEncoder<F> {
     /// Creates a new MsgEncoder.
-    pub fn new() -> MsgEncoder<ID> {
+    pub fn new() -> MsgEncoder<F> {
         MsgEncoder {
-            next_id: ID::default(),
-            sof_byte: None,
+            next_id: F::Id::default(),
             is_master: false,
         }
     }
 
     /// Returns the next message ID.
-    pub fn next_id(&mut self) -> ID {
+    pub fn next_id(&mut self) -> F::Id {
         let mut id = self.next_id;
         self.next_id.increment_id();
         if self.is_master {
@@ -65,25 +211,45 @@ where
 
     /// Resets the ID counter.
     pub fn reset(&mut self) {
-        self.next_id = ID::default();
+        self.next_id = F::Id::default();
     }
 }
 
-impl<ID, Type> Msg<ID, Type>
-where
-    ID: GenericNumber,
-    Type: BufferWritable,
-{
-    fn encode_head<W, Len, Cksum>(
+impl<F: FrameFormat> Default for MsgEncoder<F> {
+    fn default() -> MsgEncoder<F> {
+        MsgEncoder::new()
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for MsgEncoder<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MsgEncoder")
+            .field("next_id", &self.next_id)
+            .field("is_master", &self.is_master)
+            .finish()
+    }
+}
+
+impl<F: FrameFormat> Clone for MsgEncoder<F> {
+    fn clone(&self) -> MsgEncoder<F> {
+        *self
+    }
+}
+
+impl<F: FrameFormat> Copy for MsgEncoder<F> {}
+
+impl<F: FrameFormat> PartialEq for MsgEncoder<F> {
+    fn eq(&self, other: &MsgEncoder<F>) -> bool {
+        self.next_id == other.next_id && self.is_master == other.is_master
+    }
+}
+
+impl<F: FrameFormat> Msg<F> {
+    fn encode_head<W: Write>(
         &mut self,
         out: &mut W,
-        encoder: &mut MsgEncoder<ID>,
-    ) -> io::Result<()>
-    where
-        W: Write,
-        Len: GenericNumber,
-        Cksum: Checksum,
-    {
+        encoder: &mut MsgEncoder<F>,
+    ) -> io::Result<()> {
         let mut buf = Vec::with_capacity(512);
 
         let id = if self.is_response {
@@ -94,25 +260,20 @@ where
 
         self.id = id;
 
-        if let Some(sof_byte) = encoder.sof_byte {
+        if let Some(sof_byte) = F::SOF {
             buf.write_all(&[sof_byte])?;
         }
 
         id.write_to_buf(&mut buf)?;
 
-        match Len::from_usize(self.data.len()) {
+        match F::Len::from_usize(self.data.len()) {
             Some(a) => a.write_to_buf(&mut buf)?,
-            None => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Length type cannot hold message length",
-                ))
-            }
+            None => return Err(io::Error::other("Length type cannot hold message length")),
         }
 
-        self.msg_type.write_to_buf(&mut buf)?;
+        self.msg_type.to_raw().write_to_buf(&mut buf)?;
 
-        Cksum::sum(&buf).write_to_buf(&mut buf)?;
+        F::Checksum::sum(&buf).write_to_buf(&mut buf)?;
 
         out.write_all(&buf)?;
 
@@ -120,7 +281,8 @@ where
     }
 
     /// Encodes this message into the given [Write] implementor with the given encoder.
-    /// If this message is not a response, a new ID will be assigned by the encoder.
+    /// If this message is not a response, a new ID will be assigned by the encoder. Returns the ID
+    /// of the encoded message.
     ///
     /// This will consume the message to prevent potentially unnecessary allocation.
     ///
@@ -129,18 +291,28 @@ where
     /// # Examples
     /// ```
     /// # use tiny_frame::*;
-    /// let msg: Msg<u8, u8> = Msg {
+    /// enum MyFormat {}
+    ///
+    /// impl FrameFormat for MyFormat {
+    ///     type Id = u8;
+    ///     type Len = u8;
+    ///     type Type = u8;
+    ///     type Checksum = XorSum;
+    ///     const SOF: Option<u8> = Some(1); // set the sof byte to 1
+    /// }
+    ///
+    /// let msg: Msg<MyFormat> = Msg {
     ///     id: 0, // will be set when encoding since this is not a response
     ///     is_response: false,
     ///     msg_type: 0,
     ///     data: b"hello world!".to_vec(),
     /// };
-    /// let mut encoder: MsgEncoder<u8> = MsgEncoder::new();
-    /// encoder.sof_byte = Some(1); // set the sof byte to 1
+    /// let mut encoder = MsgEncoder::new();
     ///
     /// let mut bytes = Vec::new();
-    /// msg.encode::<_, u8, XorSum>(&mut bytes, &mut encoder).expect("Failed to encode");
+    /// let id = msg.encode(&mut bytes, &mut encoder).expect("Failed to encode");
     ///
+    /// assert_eq!(id, 0); // the first message sent by the encoder gets ID 0
     /// assert_eq!(bytes[0], 1); // sof byte is 1
     /// assert_eq!(bytes[1], 0); // message ID is 0 (first message by the encoder)
     /// assert_eq!(bytes[2], "hello world!".len() as u8); // message length
@@ -148,26 +320,24 @@ where
     /// // byte 4 is the Xor checksum of the message header
     /// assert_eq!(&bytes[5..17], b"hello world!"); // message content
     /// ```
-    pub fn encode<W, Len, Cksum>(
+    pub fn encode<W: Write>(
         mut self,
         out: &mut W,
-        encoder: &mut MsgEncoder<ID>,
-    ) -> io::Result<()>
-    where
-        W: Write,
-        Len: GenericNumber,
-        Cksum: Checksum,
-    {
-        self.encode_head::<W, Len, Cksum>(out, encoder)?;
-
-        Cksum::sum(&self.data).write_to_buf(&mut self.data)?;
-        out.write_all(&self.data)?;
+        encoder: &mut MsgEncoder<F>,
+    ) -> io::Result<F::Id> {
+        self.encode_head(out, encoder)?;
+
+        // frames without a body have no data checksum
+        if !self.data.is_empty() {
+            F::Checksum::sum(&self.data).write_to_buf(&mut self.data)?;
+            out.write_all(&self.data)?;
+        }
 
-        Ok(())
+        Ok(self.id)
     }
 
     /// Creates a response message to this message.
-    pub fn create_response(&self, ty: Type, data: Vec<u8>) -> Msg<ID, Type> {
+    pub fn create_response(&self, ty: F::Type, data: Vec<u8>) -> Msg<F> {
         Msg {
             id: self.id,
             is_response: true,
@@ -189,39 +359,152 @@ enum ParserState {
     DataCksum,
 }
 
+/// Errors reported by [MsgDecoder::try_accept].
+pub enum DecodeError<F: FrameFormat> {
+    /// The head checksum did not match. The rest of the frame has been skipped.
+    HeadChecksum {
+        /// The checksum calculated from the received head.
+        expected: <F::Checksum as Checksum>::Output,
+        /// The checksum that was received.
+        received: <F::Checksum as Checksum>::Output,
+    },
+
+    /// The data checksum did not match.
+    DataChecksum {
+        /// The checksum calculated from the received data.
+        expected: <F::Checksum as Checksum>::Output,
+        /// The checksum that was received.
+        received: <F::Checksum as Checksum>::Output,
+    },
+
+    /// The frame was received intact, but its type code does not convert to the message type.
+    UnknownType {
+        /// The message ID.
+        id: F::Id,
+        /// The raw type code.
+        msg_type: <F::Type as FrameType>::Raw,
+        /// The message data.
+        data: Vec<u8>,
+    },
+}
+
+impl<F: FrameFormat> fmt::Debug for DecodeError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::HeadChecksum { expected, received } => f
+                .debug_struct("HeadChecksum")
+                .field("expected", expected)
+                .field("received", received)
+                .finish(),
+            DecodeError::DataChecksum { expected, received } => f
+                .debug_struct("DataChecksum")
+                .field("expected", expected)
+                .field("received", received)
+                .finish(),
+            DecodeError::UnknownType { id, msg_type, data } => f
+                .debug_struct("UnknownType")
+                .field("id", id)
+                .field("msg_type", msg_type)
+                .field("data", data)
+                .finish(),
+        }
+    }
+}
+
+impl<F: FrameFormat> Clone for DecodeError<F> {
+    fn clone(&self) -> DecodeError<F> {
+        match self {
+            DecodeError::HeadChecksum { expected, received } => DecodeError::HeadChecksum {
+                expected: *expected,
+                received: *received,
+            },
+            DecodeError::DataChecksum { expected, received } => DecodeError::DataChecksum {
+                expected: *expected,
+                received: *received,
+            },
+            DecodeError::UnknownType { id, msg_type, data } => DecodeError::UnknownType {
+                id: *id,
+                msg_type: *msg_type,
+                data: data.clone(),
+            },
+        }
+    }
+}
+
+impl<F: FrameFormat> PartialEq for DecodeError<F> {
+    fn eq(&self, other: &DecodeError<F>) -> bool {
+        match (self, other) {
+            (
+                DecodeError::HeadChecksum { expected, received },
+                DecodeError::HeadChecksum {
+                    expected: expected2,
+                    received: received2,
+                },
+            )
+            | (
+                DecodeError::DataChecksum { expected, received },
+                DecodeError::DataChecksum {
+                    expected: expected2,
+                    received: received2,
+                },
+            ) => expected == expected2 && received == received2,
+            (
+                DecodeError::UnknownType { id, msg_type, data },
+                DecodeError::UnknownType {
+                    id: id2,
+                    msg_type: msg_type2,
+                    data: data2,
+                },
+            ) => id == id2 && msg_type == msg_type2 && data == data2,
+            _ => false,
+        }
+    }
+}
+
+impl<F: FrameFormat> fmt::Display for DecodeError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::HeadChecksum { expected, received } => write!(
+                f,
+                "head checksum mismatch (expected {:?}, received {:?})",
+                expected, received
+            ),
+            DecodeError::DataChecksum { expected, received } => write!(
+                f,
+                "data checksum mismatch (expected {:?}, received {:?})",
+                expected, received
+            ),
+            DecodeError::UnknownType { msg_type, .. } => {
+                write!(f, "unknown message type {:?}", msg_type)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: FrameFormat> std::error::Error for DecodeError<F> {}
+
 /// A TinyFrame message decoder.
-pub struct MsgDecoder<ID, Len, Type, Cksum>
-where
-    Cksum: Checksum,
-{
-    /// The start-of-frame byte. If set, frames will have to start with this byte.
-    pub sof_byte: Option<u8>,
+pub struct MsgDecoder<F: FrameFormat> {
     state: ParserState,
     part_len: usize,
-    id: ID,
-    len: Len,
-    ty: Type,
-    cksum: Cksum::Output,
+    id: F::Id,
+    len: F::Len,
+    ty: <F::Type as FrameType>::Raw,
+    cksum: <F::Checksum as Checksum>::Output,
     data: Vec<u8>,
 }
 
-impl<ID, Len, Type, Cksum> MsgDecoder<ID, Len, Type, Cksum>
-where
-    ID: GenericNumber,
-    Len: GenericNumber,
-    Type: BufferReadable + Default,
-    Cksum: Checksum,
-{
+impl<F: FrameFormat> MsgDecoder<F> {
     /// Creates a new MsgDecoder.
-    pub fn new() -> MsgDecoder<ID, Len, Type, Cksum> {
+    pub fn new() -> MsgDecoder<F> {
         MsgDecoder {
-            sof_byte: None,
             state: ParserState::Sof,
             part_len: 0,
-            id: ID::default(),
-            len: Len::default(),
-            ty: Type::default(),
-            cksum: Cksum::Output::default(),
+            id: F::Id::default(),
+            len: F::Len::default(),
+            ty: Default::default(),
+            cksum: Default::default(),
             data: Vec::new(),
         }
     }
@@ -230,30 +513,63 @@ where
     pub fn reset(&mut self) {
         self.state = ParserState::Sof;
         self.part_len = 0;
-        self.id = ID::default();
-        self.len = Len::default();
-        self.ty = Type::default();
-        self.cksum = Cksum::Output::default();
+        self.id = F::Id::default();
+        self.len = F::Len::default();
+        self.ty = Default::default();
+        self.cksum = Default::default();
         self.data = Vec::new();
     }
 
-    fn received_msg(&mut self) -> Msg<ID, Type> {
-        Msg {
-            id: self.id,
-            is_response: false,
-            msg_type: mem::replace(&mut self.ty, Type::default()),
-            data: mem::replace(&mut self.data, Vec::new()),
+    /// Moves on after the last field of the frame head. Returns the message if the frame ends
+    /// here.
+    fn end_head_fields(&mut self) -> Option<Result<Msg<F>, DecodeError<F>>> {
+        if <F::Checksum as Checksum>::Output::size() > 0 {
+            self.state = ParserState::HeadCksum;
+            self.cksum = Default::default();
+            return None;
+        }
+
+        self.data = Vec::new();
+
+        if self.len == F::Len::default() {
+            let msg = self.received_msg();
+            self.reset();
+            return Some(msg);
+        }
+
+        self.state = ParserState::Data;
+        None
+    }
+
+    fn received_msg(&mut self) -> Result<Msg<F>, DecodeError<F>> {
+        let data = mem::take(&mut self.data);
+
+        match F::Type::try_from_raw(self.ty) {
+            Ok(msg_type) => Ok(Msg {
+                id: self.id,
+                is_response: false,
+                msg_type,
+                data,
+            }),
+            Err(msg_type) => Err(DecodeError::UnknownType {
+                id: self.id,
+                msg_type,
+                data,
+            }),
         }
     }
 
     /// Accepts a single byte. Will return the received message if the frame has ended.
     ///
+    /// Frames with checksum errors or unknown message types are dropped silently; use
+    /// [MsgDecoder::try_accept] to find out about them.
+    ///
     /// # Examples
     /// ```
     /// # use tiny_frame::*;
     /// // first, encode a message
-    /// let mut encoder: MsgEncoder<u8> = MsgEncoder::new();
-    /// let mut msg: Msg<u8, u8> = Msg {
+    /// let mut encoder: MsgEncoder<DefaultFormat> = MsgEncoder::new();
+    /// let mut msg: Msg<DefaultFormat> = Msg {
     ///     id: 0, // (won’t be changed by the encoder because this is the first message)
     ///     is_response: false,
     ///     msg_type: 0,
@@ -261,22 +577,90 @@ where
     /// };
     /// let original_msg = msg.clone(); // used below
     /// let mut bytes = Vec::new();
-    /// msg.encode::<_, u8, Crc16Sum>(&mut bytes, &mut encoder).expect("Failed to encode");
+    /// msg.encode(&mut bytes, &mut encoder).expect("Failed to encode");
     ///
     /// // bytes now contains the encoded message
     ///
     /// // decode the message
-    /// let mut decoder: MsgDecoder<u8, u8, u8, Crc16Sum> = MsgDecoder::new();
-    /// for byte in bytes.into_iter() {
-    ///     if let Some(received) = decoder.accept(byte) {
-    ///         // verify that the message was encoded and decoded successfully
-    ///         assert_eq!(original_msg, received);
+    /// let mut decoder: MsgDecoder<DefaultFormat> = MsgDecoder::new();
+    /// let received = bytes.into_iter().find_map(|byte| decoder.accept(byte));
+    ///
+    /// // verify that the message was encoded and decoded successfully
+    /// assert_eq!(Some(original_msg), received);
+    ///
+    /// ```
+    pub fn accept(&mut self, byte: u8) -> Option<Msg<F>> {
+        match self.try_accept(byte) {
+            Some(Ok(msg)) => Some(msg),
+            _ => None,
+        }
+    }
+
+    /// Accepts a single byte. Will return the received message or an error if the frame has
+    /// ended.
+    ///
+    /// Unlike [MsgDecoder::accept], this reports frames that failed their checksum or whose type
+    /// code does not convert to [FrameFormat::Type] instead of dropping them.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::*;
+    /// # use tiny_frame::number::FrameType;
+    /// #[derive(Debug, Clone, Copy, PartialEq)]
+    /// #[repr(u8)]
+    /// enum Command {
+    ///     Ping = 1,
+    /// }
+    ///
+    /// impl FrameType for Command {
+    ///     type Raw = u8;
+    ///
+    ///     fn to_raw(self) -> u8 {
+    ///         self as u8
     ///     }
+    ///
+    ///     fn try_from_raw(raw: u8) -> Result<Command, u8> {
+    ///         match raw {
+    ///             1 => Ok(Command::Ping),
+    ///             raw => Err(raw),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// enum CommandFormat {}
+    ///
+    /// impl FrameFormat for CommandFormat {
+    ///     type Id = u8;
+    ///     type Len = u16;
+    ///     type Type = Command;
+    ///     type Checksum = Crc16Sum;
+    ///     const SOF: Option<u8> = Some(0x01);
+    /// }
+    ///
+    /// // a peer with a newer protocol version sends type 2
+    /// let msg: Msg<DefaultFormat> = Msg {
+    ///     id: 0,
+    ///     is_response: false,
+    ///     msg_type: 2,
+    ///     data: vec![1, 2, 3],
     /// };
+    /// let mut bytes = Vec::new();
+    /// msg.encode(&mut bytes, &mut MsgEncoder::new()).expect("Failed to encode");
+    ///
+    /// let mut decoder: MsgDecoder<CommandFormat> = MsgDecoder::new();
+    /// let received = bytes.into_iter().find_map(|byte| decoder.try_accept(byte));
     ///
+    /// assert_eq!(
+    ///     received,
+    ///     Some(Err(DecodeError::UnknownType {
+    ///         id: 0,
+    ///         msg_type: 2,
+    ///         data: vec![1, 2, 3],
+    ///     }))
+    /// );
     /// ```
-    pub fn accept(&mut self, byte: u8) -> Option<Msg<ID, Type>> {
-        if self.sof_byte.is_none() && self.state == ParserState::Sof {
+    pub fn try_accept(&mut self, byte: u8) -> Option<Result<Msg<F>, DecodeError<F>>> {
+        if F::SOF.is_none() && self.state == ParserState::Sof {
             self.reset();
             self.state = ParserState::ID;
         }
@@ -284,7 +668,7 @@ where
         macro_rules! collect_number {
             (
                 dest:$dest:expr,
-                type:$type:ident,
+                type:$type:ty,
                 byte:$byte:ident,
                 finish:$full:block,
                 debug:$debug_name:expr
@@ -292,7 +676,7 @@ where
                 $dest = $dest.add_be_byte(byte);
                 self.part_len += 1;
 
-                if self.part_len == mem::size_of::<$type>() {
+                if self.part_len == <$type>::size() {
                     self.part_len = 0;
                     $full;
                 }
@@ -302,7 +686,9 @@ where
         macro_rules! collect_cksum {
             ($full:block) => {
                 self.cksum = self.cksum.add_be_byte(byte);
-                if self.part_len == Cksum::Output::size() {
+                self.part_len += 1;
+
+                if self.part_len == <F::Checksum as Checksum>::Output::size() {
                     self.part_len = 0;
                     $full;
                 }
@@ -311,7 +697,7 @@ where
 
         match self.state {
             ParserState::Sof => {
-                if let Some(sof_byte) = self.sof_byte {
+                if let Some(sof_byte) = F::SOF {
                     if byte == sof_byte {
                         self.reset();
                         self.state = ParserState::ID;
@@ -323,7 +709,7 @@ where
                 self.data.push(byte);
                 collect_number!(
                     dest: self.id,
-                    type: ID,
+                    type: F::Id,
                     byte: byte,
                     finish: {
                         self.state = ParserState::Len;
@@ -335,9 +721,13 @@ where
                 self.data.push(byte);
                 collect_number!(
                     dest: self.len,
-                    type: Len,
+                    type: F::Len,
                     byte: byte,
                     finish: {
+                        // formats without a type field use `()`, which has no bytes to collect
+                        if <<F::Type as FrameType>::Raw>::size() == 0 {
+                            return self.end_head_fields();
+                        }
                         self.state = ParserState::Type;
                     },
                     debug: "length"
@@ -347,29 +737,26 @@ where
                 self.data.push(byte);
                 collect_number!(
                     dest: self.ty,
-                    type: Type,
+                    type: <F::Type as FrameType>::Raw,
                     byte: byte,
                     finish: {
-                        if Cksum::Output::size() == 0 {
-                            self.state = ParserState::Data;
-                        } else {
-                            self.state = ParserState::HeadCksum;
-                            self.cksum = Cksum::Output::default();
-                        }
+                        return self.end_head_fields();
                     },
                     debug: "type"
                 );
             }
             ParserState::HeadCksum => {
                 collect_cksum!({
-                    if Cksum::sum(&self.data) != self.cksum {
+                    let expected = F::Checksum::sum(&self.data);
+                    if expected != self.cksum {
+                        let received = self.cksum;
                         self.reset();
-                        return None;
+                        return Some(Err(DecodeError::HeadChecksum { expected, received }));
                     }
 
                     self.data = Vec::new();
 
-                    if self.len == Len::default() {
+                    if self.len == F::Len::default() {
                         let msg = self.received_msg();
                         self.reset();
                         return Some(msg);
@@ -382,28 +769,32 @@ where
                 self.data.push(byte);
                 self.part_len += 1;
 
-                if self.len == Len::from_usize(self.part_len).unwrap() {
-                    if Cksum::Output::size() == 0 {
+                if self.part_len == self.len.to_usize() {
+                    if <F::Checksum as Checksum>::Output::size() == 0 {
                         let msg = self.received_msg();
                         self.reset();
                         return Some(msg);
                     } else {
                         self.state = ParserState::DataCksum;
                         self.part_len = 0;
-                        self.cksum = Cksum::Output::default();
+                        self.cksum = Default::default();
                     }
                 }
             }
             ParserState::DataCksum => {
                 collect_cksum!({
-                    let msg = if Cksum::sum(&self.data) == self.cksum {
-                        Some(self.received_msg())
+                    let expected = F::Checksum::sum(&self.data);
+                    let msg = if expected == self.cksum {
+                        self.received_msg()
                     } else {
-                        None
+                        Err(DecodeError::DataChecksum {
+                            expected,
+                            received: self.cksum,
+                        })
                     };
 
                     self.reset();
-                    return msg;
+                    return Some(msg);
                 });
             }
         }
@@ -411,3 +802,9 @@ where
         None
     }
 }
+
+impl<F: FrameFormat> Default for MsgDecoder<F> {
+    fn default() -> MsgDecoder<F> {
+        MsgDecoder::new()
+    }
+}
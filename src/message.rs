@@ -0,0 +1,115 @@
+//! Typed message payloads.
+//!
+//! Instead of packing bytes into [Msg::data] by hand, payload layouts can be described by a
+//! struct implementing [TinyFrameMessage]. With the `derive` feature, this can be derived:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use tiny_frame::*;
+//!
+//! #[derive(Debug, PartialEq, TinyFrameMessage)]
+//! #[tiny_frame(format = DefaultFormat, msg_type = 0x10)]
+//! struct SetLed {
+//!     index: u8,
+//!     brightness: u16,
+//! }
+//!
+//! let msg = SetLed { index: 3, brightness: 1000 }.to_msg();
+//! assert_eq!(msg.msg_type, 0x10);
+//! assert_eq!(msg.data, [3, 0x03, 0xe8]);
+//! assert_eq!(SetLed::from_msg(&msg), Ok(SetLed { index: 3, brightness: 1000 }));
+//! # }
+//! ```
+
+use crate::number::{BufferReadable, BufferWritable};
+use crate::{FrameFormat, Msg};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Errors returned when reading a typed message from a [Msg].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageError {
+    /// The message has a different message type.
+    WrongType,
+
+    /// The payload ended before all fields were read.
+    Truncated,
+
+    /// The payload has bytes left over after all fields were read.
+    TrailingBytes,
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MessageError::WrongType => write!(f, "wrong message type"),
+            MessageError::Truncated => write!(f, "payload is truncated"),
+            MessageError::TrailingBytes => write!(f, "payload has trailing bytes"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MessageError {}
+
+/// A message with a fixed message type and payload layout.
+pub trait TinyFrameMessage<F: FrameFormat>: Sized {
+    /// The message type of this message.
+    const MSG_TYPE: F::Type;
+
+    /// Appends the payload to the buffer.
+    fn write_payload(&self, buf: &mut Vec<u8>);
+
+    /// Reads the payload from the start of the buffer and advances the buffer past it.
+    fn read_payload(buf: &mut &[u8]) -> Result<Self, MessageError>;
+
+    /// Creates a message with this payload. The ID will be assigned when encoding.
+    fn to_msg(&self) -> Msg<F> {
+        let mut data = Vec::new();
+        self.write_payload(&mut data);
+
+        Msg {
+            id: Default::default(),
+            is_response: false,
+            msg_type: Self::MSG_TYPE,
+            data,
+        }
+    }
+
+    /// Creates a response to the given message with this payload.
+    fn to_response(&self, request: &Msg<F>) -> Msg<F> {
+        let mut data = Vec::new();
+        self.write_payload(&mut data);
+
+        request.create_response(Self::MSG_TYPE, data)
+    }
+
+    /// Reads this message from a [Msg]. The message type must match and the payload must be
+    /// consumed entirely.
+    fn from_msg(msg: &Msg<F>) -> Result<Self, MessageError> {
+        if msg.msg_type != Self::MSG_TYPE {
+            return Err(MessageError::WrongType);
+        }
+
+        let mut data = &msg.data[..];
+        let payload = Self::read_payload(&mut data)?;
+
+        if data.is_empty() {
+            Ok(payload)
+        } else {
+            Err(MessageError::TrailingBytes)
+        }
+    }
+}
+
+/// Appends a big endian field to a payload buffer.
+pub fn write_field<T: BufferWritable>(value: &T, buf: &mut Vec<u8>) {
+    value
+        .write_to_buf(buf)
+        .expect("writing to a Vec cannot fail");
+}
+
+/// Reads a big endian field from the start of a payload buffer and advances the buffer past it.
+pub fn read_field<T: BufferReadable + Default>(buf: &mut &[u8]) -> Result<T, MessageError> {
+    T::read_from_buf(buf).ok_or(MessageError::Truncated)
+}
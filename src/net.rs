@@ -0,0 +1,489 @@
+//! TCP and Unix socket transports.
+//!
+//! [StreamTransport] sends and receives messages over any byte stream, such as a [TcpStream] or
+//! a [UnixStream]. [FrameListener] accepts clients on a [TcpListener] or [UnixListener] and
+//! gives every client its own transport, so each has its own decoder and ID state.
+//! [ReconnectingClient] connects to a server and reconnects with exponential backoff when the
+//! connection is lost.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use std::net::{TcpListener, TcpStream};
+//! use std::thread;
+//! use tiny_frame::net::{FrameListener, StreamTransport};
+//!
+//! let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//! let addr = listener.local_addr().unwrap();
+//! let listener = FrameListener::<DefaultFormat, _>::new(listener, false);
+//!
+//! // answer every message with its payload
+//! thread::spawn(move || {
+//!     listener.serve(
+//!         |msg| Some(msg.create_response(msg.msg_type, msg.data.clone())),
+//!         |err| eprintln!("server error: {}", err),
+//!     )
+//! });
+//!
+//! let stream = TcpStream::connect(addr).unwrap();
+//! let mut encoder = MsgEncoder::new();
+//! encoder.is_master = true;
+//! let mut client = StreamTransport::<DefaultFormat, _>::new(stream, encoder);
+//!
+//! let msg = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 0x22,
+//!     data: b"echo".to_vec(),
+//! };
+//! let id = client.send(msg).unwrap();
+//! let reply = client.recv().unwrap().unwrap();
+//! assert_eq!(reply.id, id);
+//! assert_eq!(reply.data, b"echo");
+//! ```
+
+use crate::{FrameFormat, Msg, MsgDecoder, MsgEncoder};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const READ_BUFFER_SIZE: usize = 1024;
+
+/// The shortest read timeout that [StreamTransport::run] can tell apart from a non-blocking
+/// stream.
+pub const MIN_READ_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Delays before accepting again after a transient error, as in [Backoff].
+const ACCEPT_BACKOFF: Backoff = Backoff {
+    initial: Duration::from_millis(5),
+    factor: 2,
+    max: Duration::from_secs(1),
+    max_attempts: None,
+};
+
+/// A TinyFrame connection over a byte stream.
+pub struct StreamTransport<F: FrameFormat, S> {
+    stream: S,
+    encoder: MsgEncoder<F>,
+    decoder: MsgDecoder<F>,
+    buf: Box<[u8; READ_BUFFER_SIZE]>,
+    pos: usize,
+    len: usize,
+}
+
+impl<F: FrameFormat, S: Read + Write> StreamTransport<F, S> {
+    /// Creates a new transport over a connected stream.
+    pub fn new(stream: S, encoder: MsgEncoder<F>) -> StreamTransport<F, S> {
+        StreamTransport {
+            stream,
+            encoder,
+            decoder: MsgDecoder::new(),
+            buf: Box::new([0; READ_BUFFER_SIZE]),
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the encoder.
+    pub fn encoder(&mut self) -> &mut MsgEncoder<F> {
+        &mut self.encoder
+    }
+
+    /// Returns the decoder.
+    pub fn decoder(&mut self) -> &mut MsgDecoder<F> {
+        &mut self.decoder
+    }
+
+    /// Returns the stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns the stream. Reading from it directly will corrupt the stream of frames.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Returns the encoder and the stream, discarding buffered input.
+    pub fn into_parts(self) -> (MsgEncoder<F>, S) {
+        (self.encoder, self.stream)
+    }
+
+    /// Sends a message. If the message is not a response, a new ID will be assigned. Returns the
+    /// ID of the message.
+    ///
+    /// The frame is written with a single `write_all` call.
+    pub fn send(&mut self, msg: Msg<F>) -> io::Result<F::Id> {
+        let mut buf = Vec::new();
+        let id = msg.encode(&mut buf, &mut self.encoder)?;
+        self.stream.write_all(&buf)?;
+        self.stream.flush()?;
+        Ok(id)
+    }
+
+    /// Receives the next message. Returns `None` if the stream has a read timeout or is
+    /// non-blocking and no complete message arrived in time; a partially received frame is
+    /// kept and completed by the next call. Returns an
+    /// [UnexpectedEof](io::ErrorKind::UnexpectedEof) error once the peer closed the connection.
+    ///
+    /// Frames that fail to decode are dropped, as with [MsgDecoder::accept].
+    pub fn recv(&mut self) -> io::Result<Option<Msg<F>>> {
+        match self.read_msg() {
+            Ok(msg) => Ok(Some(msg)),
+            Err(err)
+                if err.kind() == io::ErrorKind::WouldBlock
+                    || err.kind() == io::ErrorKind::TimedOut =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Receives the next message, like [recv](Self::recv), but returns read timeouts as errors.
+    fn read_msg(&mut self) -> io::Result<Msg<F>> {
+        loop {
+            while self.pos < self.len {
+                let byte = self.buf[self.pos];
+                self.pos += 1;
+                if let Some(msg) = self.decoder.accept(byte) {
+                    return Ok(msg);
+                }
+            }
+
+            match self.stream.read(&mut self.buf[..]) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => {
+                    self.pos = 0;
+                    self.len = len;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Receives messages and passes them to `handler` until the peer closes the connection or
+    /// it fails. If the handler returns a message, it is sent.
+    ///
+    /// Read timeouts are waited out, but the stream must be blocking: on a non-blocking stream,
+    /// this fails with [WouldBlock](io::ErrorKind::WouldBlock) once no data is available. As
+    /// Unix sockets report read timeouts as `WouldBlock` as well, a `WouldBlock` error is only
+    /// taken for a timeout if the read blocked for at least [MIN_READ_TIMEOUT].
+    pub fn run<H>(&mut self, mut handler: H) -> io::Result<()>
+    where
+        H: FnMut(Msg<F>) -> Option<Msg<F>>,
+    {
+        loop {
+            let started = Instant::now();
+            let msg = match self.read_msg() {
+                Ok(msg) => msg,
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => continue,
+                Err(err)
+                    if err.kind() == io::ErrorKind::WouldBlock
+                        && started.elapsed() >= MIN_READ_TIMEOUT =>
+                {
+                    continue
+                }
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(err) => return Err(err),
+            };
+
+            if let Some(reply) = handler(msg) {
+                self.send(reply)?;
+            }
+        }
+    }
+}
+
+impl<F: FrameFormat, S: fmt::Debug> fmt::Debug for StreamTransport<F, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StreamTransport")
+            .field("stream", &self.stream)
+            .field("encoder", &self.encoder)
+            .field("buffered", &(self.len - self.pos))
+            .finish_non_exhaustive()
+    }
+}
+
+/// A listening socket that [FrameListener] can accept connections on.
+pub trait Listen {
+    /// The type of the accepted connections.
+    type Stream: Read + Write + Send + 'static;
+
+    /// Accepts a new connection.
+    fn accept_stream(&self) -> io::Result<Self::Stream>;
+}
+
+impl Listen for TcpListener {
+    type Stream = TcpStream;
+
+    fn accept_stream(&self) -> io::Result<TcpStream> {
+        let (stream, _) = self.accept()?;
+        // frames are written in one piece, so there is nothing to gain from Nagle's algorithm
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+}
+
+#[cfg(unix)]
+impl Listen for UnixListener {
+    type Stream = UnixStream;
+
+    fn accept_stream(&self) -> io::Result<UnixStream> {
+        self.accept().map(|(stream, _)| stream)
+    }
+}
+
+/// Accepts clients on a listening socket.
+pub struct FrameListener<F: FrameFormat, L> {
+    listener: L,
+    is_master: bool,
+    _format: PhantomData<fn() -> F>,
+}
+
+impl<F: FrameFormat, L: Listen> FrameListener<F, L> {
+    /// Creates a new listener. `is_master` is the role of the listening side, which is used for
+    /// the encoders of all clients.
+    pub fn new(listener: L, is_master: bool) -> FrameListener<F, L> {
+        FrameListener {
+            listener,
+            is_master,
+            _format: PhantomData,
+        }
+    }
+
+    /// Returns the listening socket.
+    pub fn get_ref(&self) -> &L {
+        &self.listener
+    }
+
+    /// Accepts a client and returns a transport with a fresh encoder and decoder.
+    pub fn accept(&self) -> io::Result<StreamTransport<F, L::Stream>> {
+        let stream = self.listener.accept_stream()?;
+        let mut encoder = MsgEncoder::new();
+        encoder.is_master = self.is_master;
+        Ok(StreamTransport::new(stream, encoder))
+    }
+
+    /// Accepts clients and handles each of them on its own thread, passing received messages
+    /// to `handler` and sending the messages it returns back to the same client.
+    ///
+    /// Errors that end a client's connection, and transient errors when accepting a connection
+    /// (such as an aborted connection or running out of file descriptors), are passed to
+    /// `on_error`; after the latter, accepting is retried with a short backoff. Only returns if
+    /// accepting a connection fails with any other error.
+    pub fn serve<H, E>(&self, handler: H, on_error: E) -> io::Result<()>
+    where
+        F: 'static,
+        StreamTransport<F, L::Stream>: Send,
+        H: Fn(Msg<F>) -> Option<Msg<F>> + Send + Sync + 'static,
+        E: Fn(io::Error) + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        let on_error = Arc::new(on_error);
+        let mut delay = ACCEPT_BACKOFF.initial;
+        loop {
+            let mut client = match self.accept() {
+                Ok(client) => client,
+                Err(err) if is_transient(&err) => {
+                    on_error(err);
+                    thread::sleep(delay);
+                    delay = delay
+                        .saturating_mul(ACCEPT_BACKOFF.factor)
+                        .min(ACCEPT_BACKOFF.max);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            delay = ACCEPT_BACKOFF.initial;
+
+            let handler = Arc::clone(&handler);
+            let on_error = Arc::clone(&on_error);
+            thread::spawn(move || {
+                if let Err(err) = client.run(|msg| handler(msg)) {
+                    on_error(err);
+                }
+            });
+        }
+    }
+}
+
+/// Returns true if an error from accepting a connection does not affect later connections.
+fn is_transient(err: &io::Error) -> bool {
+    // EMFILE, ENFILE and ENOMEM, which have no error kind; the values are the same on all Unix
+    // systems. Windows reports too many open sockets as WSAEMFILE.
+    const RESOURCE_ERRORS: &[i32] = if cfg!(windows) {
+        &[10024]
+    } else {
+        &[24, 23, 12]
+    };
+
+    match err.kind() {
+        io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::Interrupted
+        | io::ErrorKind::OutOfMemory => true,
+        _ => err
+            .raw_os_error()
+            .is_some_and(|code| RESOURCE_ERRORS.contains(&code)),
+    }
+}
+
+impl<F: FrameFormat, L: fmt::Debug> fmt::Debug for FrameListener<F, L> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FrameListener")
+            .field("listener", &self.listener)
+            .field("is_master", &self.is_master)
+            .finish()
+    }
+}
+
+/// Exponential backoff between connection attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Backoff {
+    /// The delay after the first failed attempt.
+    pub initial: Duration,
+
+    /// The factor the delay is multiplied with after every failed attempt.
+    pub factor: u32,
+
+    /// The maximum delay.
+    pub max: Duration,
+
+    /// The number of attempts after which connecting fails, or `None` to retry forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    /// 100 ms, doubling up to 10 s, retrying forever.
+    fn default() -> Backoff {
+        Backoff {
+            initial: Duration::from_millis(100),
+            factor: 2,
+            max: Duration::from_secs(10),
+            max_attempts: None,
+        }
+    }
+}
+
+/// A client that reconnects when the connection is lost.
+///
+/// The encoder is kept across connections, so message IDs keep counting up; the decoder starts
+/// over with every connection.
+pub struct ReconnectingClient<F: FrameFormat, S, C> {
+    connect: C,
+    backoff: Backoff,
+    encoder: Option<MsgEncoder<F>>,
+    transport: Option<StreamTransport<F, S>>,
+}
+
+impl<F, S, C> ReconnectingClient<F, S, C>
+where
+    F: FrameFormat,
+    S: Read + Write,
+    C: FnMut() -> io::Result<S>,
+{
+    /// Creates a new client that connects by calling `connect`. It does not connect until it
+    /// is used.
+    pub fn new(
+        connect: C,
+        encoder: MsgEncoder<F>,
+        backoff: Backoff,
+    ) -> ReconnectingClient<F, S, C> {
+        ReconnectingClient {
+            connect,
+            backoff,
+            encoder: Some(encoder),
+            transport: None,
+        }
+    }
+
+    /// Returns true if the client is connected.
+    pub fn is_connected(&self) -> bool {
+        self.transport.is_some()
+    }
+
+    /// Returns the current connection, connecting first if needed. Blocks while waiting between
+    /// attempts, and fails once [max_attempts](Backoff::max_attempts) is reached.
+    pub fn connect(&mut self) -> io::Result<&mut StreamTransport<F, S>> {
+        if self.transport.is_none() {
+            let mut delay = self.backoff.initial;
+            let mut attempts = 0;
+            let stream = loop {
+                match (self.connect)() {
+                    Ok(stream) => break stream,
+                    Err(err) => {
+                        attempts += 1;
+                        if self.backoff.max_attempts.is_some_and(|max| attempts >= max) {
+                            return Err(err);
+                        }
+                    }
+                }
+                thread::sleep(delay);
+                delay = delay
+                    .saturating_mul(self.backoff.factor)
+                    .min(self.backoff.max);
+            };
+
+            let encoder = self
+                .encoder
+                .take()
+                .expect("encoder is kept while disconnected");
+            self.transport = Some(StreamTransport::new(stream, encoder));
+        }
+
+        Ok(self.transport.as_mut().expect("connected"))
+    }
+
+    /// Closes the current connection. The next call will reconnect.
+    pub fn disconnect(&mut self) {
+        if let Some(transport) = self.transport.take() {
+            self.encoder = Some(transport.into_parts().0);
+        }
+    }
+
+    /// Sends a message, connecting first if needed. If sending fails, the connection is closed
+    /// and the error is returned; the message is not resent.
+    pub fn send(&mut self, msg: Msg<F>) -> io::Result<F::Id> {
+        let result = self.connect()?.send(msg);
+        if result.is_err() {
+            self.disconnect();
+        }
+        result
+    }
+
+    /// Receives the next message, connecting first if needed. See [StreamTransport::recv]. If
+    /// the connection is lost, it is closed and the error is returned.
+    pub fn recv(&mut self) -> io::Result<Option<Msg<F>>> {
+        let result = self.connect()?.recv();
+        if result.is_err() {
+            self.disconnect();
+        }
+        result
+    }
+}
+
+impl<F: FrameFormat, S, C> fmt::Debug for ReconnectingClient<F, S, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReconnectingClient")
+            .field("backoff", &self.backoff)
+            .field("connected", &self.transport.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns a function that connects to a TCP address, for use with [ReconnectingClient].
+pub fn tcp_connector(addr: SocketAddr) -> impl FnMut() -> io::Result<TcpStream> {
+    move || {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+}
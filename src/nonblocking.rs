@@ -0,0 +1,160 @@
+//! Encoding into non-blocking outputs.
+//!
+//! [Msg::encode] uses [Write::write_all], which blocks on a blocking output and fails halfway
+//! through a frame on a non-blocking one, leaving a torn frame on the wire. [NonBlockingEncoder]
+//! instead encodes messages into a queue, and [poll_flush](NonBlockingEncoder::poll_flush) writes
+//! as much of it as the output accepts. When the output returns
+//! [WouldBlock](io::ErrorKind::WouldBlock), it stops and resumes at exactly the same byte on the
+//! next call, so frames are never torn or duplicated.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use std::io::{self, Write};
+//! use tiny_frame::nonblocking::NonBlockingEncoder;
+//!
+//! // an output that accepts 4 bytes at a time
+//! struct Uart {
+//!     sent: Vec<u8>,
+//!     ready: usize,
+//! }
+//!
+//! impl Write for Uart {
+//!     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+//!         if self.ready == 0 {
+//!             return Err(io::ErrorKind::WouldBlock.into());
+//!         }
+//!         let len = buf.len().min(self.ready);
+//!         self.sent.extend_from_slice(&buf[..len]);
+//!         self.ready -= len;
+//!         Ok(len)
+//!     }
+//!
+//!     fn flush(&mut self) -> io::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut encoder = NonBlockingEncoder::<DefaultFormat>::new(MsgEncoder::new());
+//! let msg = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 0x10,
+//!     data: b"hello".to_vec(),
+//! };
+//! encoder.queue(msg).unwrap();
+//!
+//! let mut uart = Uart { sent: Vec::new(), ready: 4 };
+//! assert!(encoder.poll_flush(&mut uart).is_pending());
+//! assert_eq!(encoder.pending(), 14 - 4);
+//!
+//! // the output became writable again
+//! uart.ready = 100;
+//! assert!(encoder.poll_flush(&mut uart).is_ready());
+//! assert!(encoder.is_empty());
+//!
+//! let mut decoder = MsgDecoder::<DefaultFormat>::new();
+//! let received = uart.sent.iter().find_map(|&b| decoder.accept(b)).unwrap();
+//! assert_eq!(received.data, b"hello");
+//! ```
+
+use crate::{FrameFormat, Msg, MsgEncoder};
+use std::fmt;
+use std::io::{self, Write};
+use std::task::Poll;
+
+/// A message encoder that queues frames and writes them to a non-blocking output.
+pub struct NonBlockingEncoder<F: FrameFormat> {
+    encoder: MsgEncoder<F>,
+    buf: Vec<u8>,
+    written: usize,
+}
+
+impl<F: FrameFormat> NonBlockingEncoder<F> {
+    /// Creates a new encoder with an empty queue.
+    pub fn new(encoder: MsgEncoder<F>) -> NonBlockingEncoder<F> {
+        NonBlockingEncoder {
+            encoder,
+            buf: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Returns the inner encoder.
+    pub fn encoder(&mut self) -> &mut MsgEncoder<F> {
+        &mut self.encoder
+    }
+
+    /// Encodes a message and adds the frame to the queue. If the message is not a response, a
+    /// new ID will be assigned. Returns the ID of the message.
+    pub fn queue(&mut self, msg: Msg<F>) -> io::Result<F::Id> {
+        // drop the part that was already written
+        self.buf.drain(..self.written);
+        self.written = 0;
+
+        msg.encode(&mut self.buf, &mut self.encoder)
+    }
+
+    /// Returns the number of queued bytes that were not written yet.
+    pub fn pending(&self) -> usize {
+        self.buf.len() - self.written
+    }
+
+    /// Returns true if all queued frames were written.
+    pub fn is_empty(&self) -> bool {
+        self.pending() == 0
+    }
+
+    /// Discards all queued bytes, including the rest of a partially written frame.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.written = 0;
+    }
+
+    /// Writes as much of the queue as the output accepts, then flushes it.
+    ///
+    /// Returns [Poll::Pending] if the output returned [WouldBlock](io::ErrorKind::WouldBlock);
+    /// call this again once it is writable. Returns `Poll::Ready(Ok(()))` once the queue is
+    /// empty and the output was flushed. Other errors are returned as they are, and writing
+    /// can be resumed after them as well.
+    pub fn poll_flush<W: Write>(&mut self, out: &mut W) -> Poll<io::Result<()>> {
+        while self.written < self.buf.len() {
+            match out.write(&self.buf[self.written..]) {
+                Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Ok(len) => self.written += len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+        self.clear();
+
+        loop {
+            match out.flush() {
+                Ok(()) => return Poll::Ready(Ok(())),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+    }
+
+    /// Like [poll_flush](Self::poll_flush), but returns a
+    /// [WouldBlock](io::ErrorKind::WouldBlock) error if the queue could not be written
+    /// completely.
+    pub fn try_flush<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        match self.poll_flush(out) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for NonBlockingEncoder<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NonBlockingEncoder")
+            .field("encoder", &self.encoder)
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
@@ -1,5 +1,7 @@
-use std::io::{self, Write};
-use std::mem;
+use crate::io::{self, Write};
+use core::fmt;
+use core::hash::Hash;
+use core::mem;
 
 /// A number type that can be written to a buffer using big endian encoding.
 pub trait BufferWritable {
@@ -54,6 +56,30 @@ pub trait BufferReadable {
 
     /// Returns the size of this type.
     fn size() -> usize;
+
+    /// Reads a big endian value from the start of the buffer and advances the buffer past it.
+    /// Returns None if the buffer is too short.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::number::BufferReadable;
+    /// let mut buffer: &[u8] = &[12, 1, 24];
+    /// assert_eq!(u8::read_from_buf(&mut buffer), Some(12));
+    /// assert_eq!(u16::read_from_buf(&mut buffer), Some(280));
+    /// assert_eq!(u16::read_from_buf(&mut buffer), None);
+    /// ```
+    fn read_from_buf(buf: &mut &[u8]) -> Option<Self>
+    where
+        Self: Default + Sized,
+    {
+        if buf.len() < Self::size() {
+            return None;
+        }
+
+        let (bytes, rest) = buf.split_at(Self::size());
+        *buf = rest;
+        Some(bytes.iter().fold(Self::default(), |n, &b| n.add_be_byte(b)))
+    }
 }
 
 macro_rules! buffer_readable_byte_impl {
@@ -103,50 +129,223 @@ buffer_readable_impl!(i64);
 buffer_readable_impl!(i128);
 
 /// A generic number trait.
-pub trait GenericNumber: BufferReadable + BufferWritable + Default + Copy + PartialEq {
-    /// Increments this ID.
+///
+/// This is implemented for all fixed-size values that can be read from and written to a frame.
+/// Frame fields additionally require one of the role-specific traits [FrameId], [FrameLen] and
+/// [FrameType].
+pub trait GenericNumber:
+    BufferReadable + BufferWritable + Default + Copy + PartialEq + fmt::Debug
+{
+}
+
+impl<T> GenericNumber for T where
+    T: BufferReadable + BufferWritable + Default + Copy + PartialEq + fmt::Debug
+{
+}
+
+/// A number type that can be used for the frame ID field.
+///
+/// The highest bit of the ID is the master peer bit, so that both peers can allocate IDs
+/// without colliding.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as a frame ID",
+    note = "frame IDs must be unsigned integers: u8, u16, u32 or u64"
+)]
+pub trait FrameId: GenericNumber + Eq + Hash {
+    /// Increments this ID, wrapping around before it reaches the master peer bit.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::number::FrameId;
+    /// let mut id = 0x7fu8;
+    /// id.increment_id();
+    /// assert_eq!(id, 0);
+    /// ```
     fn increment_id(&mut self);
 
     /// Adds the master peer bit to this ID.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::number::FrameId;
+    /// let mut id = 3u16;
+    /// id.add_master_peer_bit();
+    /// assert_eq!(id, 0x8003);
+    /// assert!(id.has_master_peer_bit());
+    /// ```
     fn add_master_peer_bit(&mut self);
 
-    /// Converts a `usize` to this length type.
+    /// Returns true if this ID has the master peer bit set.
+    fn has_master_peer_bit(&self) -> bool;
+}
+
+/// A number type that can be used for the frame length field.
+///
+/// Only unsigned integers are frame lengths, so formats without a length field do not compile:
+/// ```compile_fail
+/// # use tiny_frame::*;
+/// enum NoLength {}
+///
+/// impl FrameFormat for NoLength {
+///     type Id = u8;
+///     type Len = (); // error: `()` cannot be used as a frame length
+///     type Type = u8;
+///     type Checksum = XorSum;
+///     const SOF: Option<u8> = None;
+/// }
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as a frame length",
+    note = "frame lengths must be unsigned integers: u8, u16, u32 or u64"
+)]
+pub trait FrameLen: GenericNumber {
+    /// Converts a `usize` to this length type. Returns None if the length does not fit.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::number::FrameLen;
+    /// assert_eq!(u8::from_usize(255), Some(255));
+    /// assert_eq!(u8::from_usize(256), None);
+    /// ```
     fn from_usize(size: usize) -> Option<Self>;
+
+    /// Converts this length to a `usize`.
+    fn to_usize(self) -> usize;
+}
+
+/// A type that can be used for the frame type field.
+///
+/// Message types are transmitted as a raw integer. Besides plain integers, this can be
+/// implemented for enums so that the received type codes are checked when decoding; codes that
+/// do not convert are reported by [MsgDecoder::try_accept](crate::MsgDecoder::try_accept).
+///
+/// # Examples
+/// ```
+/// # use tiny_frame::number::FrameType;
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// #[repr(u8)]
+/// enum Command {
+///     Ping = 1,
+///     Reset = 2,
+/// }
+///
+/// impl FrameType for Command {
+///     type Raw = u8;
+///
+///     fn to_raw(self) -> u8 {
+///         self as u8
+///     }
+///
+///     fn try_from_raw(raw: u8) -> Result<Command, u8> {
+///         match raw {
+///             1 => Ok(Command::Ping),
+///             2 => Ok(Command::Reset),
+///             raw => Err(raw),
+///         }
+///     }
+/// }
+///
+/// assert_eq!(Command::try_from_raw(2), Ok(Command::Reset));
+/// assert_eq!(Command::try_from_raw(3), Err(3));
+/// ```
+///
+/// Formats without a type field use `()`:
+/// ```
+/// # use tiny_frame::*;
+/// enum Untyped {}
+///
+/// impl FrameFormat for Untyped {
+///     type Id = u8;
+///     type Len = u8;
+///     type Type = ();
+///     type Checksum = XorSum;
+///     const SOF: Option<u8> = Some(0x01);
+/// }
+///
+/// let msg: Msg<Untyped> = Msg {
+///     id: 0,
+///     is_response: false,
+///     msg_type: (),
+///     data: vec![1, 2, 3],
+/// };
+/// let mut bytes = Vec::new();
+/// msg.clone().encode(&mut bytes, &mut MsgEncoder::new()).unwrap();
+/// assert_eq!(bytes, [1, 0, 3, 253, 1, 2, 3, 255]);
+///
+/// let mut decoder: MsgDecoder<Untyped> = MsgDecoder::new();
+/// assert_eq!(bytes.iter().find_map(|&b| decoder.accept(b)), Some(msg));
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as a frame type",
+    note = "frame types must be integers, `()`, or implement `FrameType` to convert from one"
+)]
+pub trait FrameType: Copy + PartialEq {
+    /// The integer type that is transmitted in the type field.
+    type Raw: GenericNumber;
+
+    /// Converts this message type to its raw value.
+    fn to_raw(self) -> Self::Raw;
+
+    /// Converts a raw value to a message type. Returns the raw value if it does not correspond
+    /// to any message type.
+    fn try_from_raw(raw: Self::Raw) -> Result<Self, Self::Raw>;
 }
 
-macro_rules! generic_number_impl {
-    ($type:ty, $type2:ident) => {
-        impl GenericNumber for $type {
+macro_rules! frame_type_impl {
+    ($type:ty) => {
+        impl FrameType for $type {
+            type Raw = $type;
+
+            fn to_raw(self) -> $type {
+                self
+            }
+            fn try_from_raw(raw: $type) -> Result<Self, $type> {
+                Ok(raw)
+            }
+        }
+    };
+}
+
+frame_type_impl!(());
+frame_type_impl!(u8);
+frame_type_impl!(u16);
+frame_type_impl!(u32);
+frame_type_impl!(u64);
+frame_type_impl!(i8);
+frame_type_impl!(i16);
+frame_type_impl!(i32);
+frame_type_impl!(i64);
+
+macro_rules! frame_number_impl {
+    ($type:ident) => {
+        impl FrameId for $type {
             fn increment_id(&mut self) {
-                *self = self.wrapping_add(1) & ($type2::max_value() >> 1);
+                *self = self.wrapping_add(1) & ($type::MAX >> 1);
             }
             fn add_master_peer_bit(&mut self) {
-                *self |= 1 << mem::size_of::<$type>() * 8 - 1;
+                *self |= !($type::MAX >> 1);
+            }
+            fn has_master_peer_bit(&self) -> bool {
+                *self & !($type::MAX >> 1) != 0
             }
+        }
+
+        impl FrameLen for $type {
             fn from_usize(size: usize) -> Option<Self> {
-                if size > $type2::max_value() as usize {
+                if size as u128 > $type::MAX as u128 {
                     None
                 } else {
                     Some(size as $type)
                 }
             }
+            fn to_usize(self) -> usize {
+                self as usize
+            }
         }
     };
 }
 
-impl GenericNumber for () {
-    fn increment_id(&mut self) {}
-    fn add_master_peer_bit(&mut self) {}
-    fn from_usize(_: usize) -> Option<()> {
-        None
-    }
-}
-
-generic_number_impl!(u8, u8);
-generic_number_impl!(i8, i8);
-generic_number_impl!(u16, u16);
-generic_number_impl!(i16, i16);
-generic_number_impl!(u32, u32);
-generic_number_impl!(i32, i32);
-generic_number_impl!(u64, u64);
-generic_number_impl!(i64, i64);
+frame_number_impl!(u8);
+frame_number_impl!(u16);
+frame_number_impl!(u32);
+frame_number_impl!(u64);
@@ -0,0 +1,446 @@
+//! Payload building and parsing utilities.
+//!
+//! This is the counterpart of upstream TinyFrame's `payload_builder.h` and `payload_parser.h`.
+//! [PayloadBuilder] appends values to a payload buffer and [PayloadParser] reads them back with
+//! bounds checking. Both remember whether a value did not fit, so that a sequence of operations
+//! can be checked once at the end.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! # use tiny_frame::payload::*;
+//! let mut builder = PayloadBuilder::new();
+//! builder.u8(1).u16(1000).f32(0.5).bool(true).nul_str("hello");
+//!
+//! let msg: Msg<DefaultFormat> = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 1,
+//!     data: builder.finish(),
+//! };
+//!
+//! let mut parser = PayloadParser::new(&msg.data);
+//! assert_eq!(parser.u8(), Some(1));
+//! assert_eq!(parser.u16(), Some(1000));
+//! assert_eq!(parser.f32(), Some(0.5));
+//! assert_eq!(parser.bool(), Some(true));
+//! assert_eq!(parser.nul_str(), Some("hello"));
+//! assert_eq!(parser.u8(), None);
+//! assert!(parser.has_overrun());
+//! ```
+
+use crate::number::FrameLen;
+use alloc::vec::Vec;
+use core::mem;
+
+/// Byte order of multi-byte values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endian {
+    /// Big endian (network byte order). This is the byte order of the frame head.
+    #[default]
+    Big,
+
+    /// Little endian.
+    Little,
+}
+
+/// A fixed-size value that can be stored in a payload.
+pub trait PayloadValue: Sized + Copy {
+    /// The size of the value in bytes.
+    const SIZE: usize;
+
+    /// Appends the value to the buffer.
+    fn write(self, endian: Endian, buf: &mut Vec<u8>);
+
+    /// Reads the value from exactly [PayloadValue::SIZE] bytes.
+    fn read(endian: Endian, bytes: &[u8]) -> Self;
+}
+
+macro_rules! payload_value_impl {
+    ($type:ty) => {
+        impl PayloadValue for $type {
+            const SIZE: usize = mem::size_of::<$type>();
+
+            fn write(self, endian: Endian, buf: &mut Vec<u8>) {
+                match endian {
+                    Endian::Big => buf.extend_from_slice(&self.to_be_bytes()),
+                    Endian::Little => buf.extend_from_slice(&self.to_le_bytes()),
+                }
+            }
+
+            fn read(endian: Endian, bytes: &[u8]) -> Self {
+                let mut buf = [0; mem::size_of::<$type>()];
+                buf.copy_from_slice(bytes);
+                match endian {
+                    Endian::Big => <$type>::from_be_bytes(buf),
+                    Endian::Little => <$type>::from_le_bytes(buf),
+                }
+            }
+        }
+    };
+}
+
+payload_value_impl!(u8);
+payload_value_impl!(u16);
+payload_value_impl!(u32);
+payload_value_impl!(u64);
+payload_value_impl!(i8);
+payload_value_impl!(i16);
+payload_value_impl!(i32);
+payload_value_impl!(i64);
+payload_value_impl!(f32);
+payload_value_impl!(f64);
+
+impl PayloadValue for bool {
+    const SIZE: usize = 1;
+
+    fn write(self, _: Endian, buf: &mut Vec<u8>) {
+        buf.push(self as u8);
+    }
+
+    fn read(_: Endian, bytes: &[u8]) -> bool {
+        bytes[0] != 0
+    }
+}
+
+/// Builds a payload from a sequence of values.
+///
+/// If a value would make the payload longer than the limit, neither it nor any following value
+/// is written and the builder is marked as [overrun](PayloadBuilder::has_overrun).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadBuilder {
+    data: Vec<u8>,
+    endian: Endian,
+    limit: usize,
+    overrun: bool,
+}
+
+macro_rules! builder_methods {
+    ($($name:ident: $type:ty,)*) => {
+        $(
+            #[doc = concat!("Appends a `", stringify!($type), "`.")]
+            pub fn $name(&mut self, value: $type) -> &mut PayloadBuilder {
+                self.put(value)
+            }
+        )*
+    };
+}
+
+impl PayloadBuilder {
+    /// Creates a new big endian PayloadBuilder without a length limit.
+    pub fn new() -> PayloadBuilder {
+        PayloadBuilder {
+            data: Vec::new(),
+            endian: Endian::Big,
+            limit: usize::MAX,
+            overrun: false,
+        }
+    }
+
+    /// Sets the byte order of multi-byte values.
+    pub fn with_endian(mut self, endian: Endian) -> PayloadBuilder {
+        self.endian = endian;
+        self
+    }
+
+    /// Sets the maximum payload length, e.g. the largest value of the format's length type.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::payload::PayloadBuilder;
+    /// let mut builder = PayloadBuilder::new().with_limit(4);
+    /// builder.u16(1).u16(2).u8(3);
+    /// assert!(builder.has_overrun());
+    /// assert_eq!(builder.finish(), [0, 1, 0, 2]);
+    ///
+    /// // a limit below the current length rejects all further values
+    /// let mut builder = PayloadBuilder::new();
+    /// builder.u32(1);
+    /// let mut builder = builder.with_limit(2);
+    /// builder.u8(2);
+    /// assert!(builder.has_overrun());
+    /// ```
+    pub fn with_limit(mut self, limit: usize) -> PayloadBuilder {
+        self.limit = limit;
+        self
+    }
+
+    /// Returns true if a value did not fit into the payload.
+    pub fn has_overrun(&self) -> bool {
+        self.overrun
+    }
+
+    /// Returns the current length of the payload.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if nothing has been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the payload.
+    pub fn finish(self) -> Vec<u8> {
+        self.data
+    }
+
+    fn reserve(&mut self, len: usize) -> bool {
+        if self.overrun || len > self.limit.saturating_sub(self.data.len()) {
+            self.overrun = true;
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Appends a value.
+    pub fn put<T: PayloadValue>(&mut self, value: T) -> &mut PayloadBuilder {
+        if self.reserve(T::SIZE) {
+            value.write(self.endian, &mut self.data);
+        }
+        self
+    }
+
+    builder_methods! {
+        u8: u8,
+        u16: u16,
+        u32: u32,
+        u64: u64,
+        i8: i8,
+        i16: i16,
+        i32: i32,
+        i64: i64,
+        f32: f32,
+        f64: f64,
+        bool: bool,
+    }
+
+    /// Appends raw bytes.
+    pub fn bytes(&mut self, bytes: &[u8]) -> &mut PayloadBuilder {
+        if self.reserve(bytes.len()) {
+            self.data.extend_from_slice(bytes);
+        }
+        self
+    }
+
+    /// Appends bytes preceded by their length as an `L`. Marks the builder as overrun if the
+    /// length does not fit into `L`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::payload::PayloadBuilder;
+    /// let mut builder = PayloadBuilder::new();
+    /// builder.prefixed_bytes::<u16>(&[1, 2, 3]);
+    /// assert_eq!(builder.finish(), [0, 3, 1, 2, 3]);
+    /// ```
+    pub fn prefixed_bytes<L>(&mut self, bytes: &[u8]) -> &mut PayloadBuilder
+    where
+        L: PayloadValue + FrameLen,
+    {
+        match L::from_usize(bytes.len()) {
+            Some(len) if self.reserve(L::SIZE + bytes.len()) => {
+                len.write(self.endian, &mut self.data);
+                self.data.extend_from_slice(bytes);
+            }
+            _ => self.overrun = true,
+        }
+        self
+    }
+
+    /// Appends a string preceded by its length in bytes as an `L`.
+    pub fn prefixed_str<L>(&mut self, string: &str) -> &mut PayloadBuilder
+    where
+        L: PayloadValue + FrameLen,
+    {
+        self.prefixed_bytes::<L>(string.as_bytes())
+    }
+
+    /// Appends a string followed by a null byte. Marks the builder as overrun if the string
+    /// contains a null byte, because it could not be read back.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::payload::PayloadBuilder;
+    /// let mut builder = PayloadBuilder::new();
+    /// builder.nul_str("hi");
+    /// assert!(!builder.has_overrun());
+    /// builder.nul_str("a\0b");
+    /// assert!(builder.has_overrun());
+    /// assert_eq!(builder.finish(), b"hi\0");
+    /// ```
+    pub fn nul_str(&mut self, string: &str) -> &mut PayloadBuilder {
+        if string.as_bytes().contains(&0) {
+            self.overrun = true;
+        } else if self.reserve(string.len() + 1) {
+            self.data.extend_from_slice(string.as_bytes());
+            self.data.push(0);
+        }
+        self
+    }
+}
+
+impl Default for PayloadBuilder {
+    fn default() -> PayloadBuilder {
+        PayloadBuilder::new()
+    }
+}
+
+/// Reads values from a payload.
+///
+/// All reads are checked and return None if there is not enough data left, in which case the
+/// parser is marked as [overrun](PayloadParser::has_overrun) and the position is not changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    endian: Endian,
+    overrun: bool,
+}
+
+macro_rules! parser_methods {
+    ($($name:ident: $type:ty,)*) => {
+        $(
+            #[doc = concat!("Reads a `", stringify!($type), "`.")]
+            pub fn $name(&mut self) -> Option<$type> {
+                self.get()
+            }
+        )*
+    };
+}
+
+impl<'a> PayloadParser<'a> {
+    /// Creates a new big endian PayloadParser.
+    pub fn new(data: &'a [u8]) -> PayloadParser<'a> {
+        PayloadParser {
+            data,
+            pos: 0,
+            endian: Endian::Big,
+            overrun: false,
+        }
+    }
+
+    /// Sets the byte order of multi-byte values.
+    pub fn with_endian(mut self, endian: Endian) -> PayloadParser<'a> {
+        self.endian = endian;
+        self
+    }
+
+    /// Returns true if a read went past the end of the payload.
+    pub fn has_overrun(&self) -> bool {
+        self.overrun
+    }
+
+    /// Returns the current position in the payload.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the number of bytes left.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    /// Returns all remaining bytes and moves to the end of the payload.
+    pub fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.pos..];
+        self.pos = self.data.len();
+        rest
+    }
+
+    /// Reads a value.
+    pub fn get<T: PayloadValue>(&mut self) -> Option<T> {
+        let endian = self.endian;
+        self.bytes(T::SIZE).map(|bytes| T::read(endian, bytes))
+    }
+
+    parser_methods! {
+        u8: u8,
+        u16: u16,
+        u32: u32,
+        u64: u64,
+        i8: i8,
+        i16: i16,
+        i32: i32,
+        i64: i64,
+        f32: f32,
+        f64: f64,
+        bool: bool,
+    }
+
+    /// Reads the given number of raw bytes.
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.remaining() {
+            self.overrun = true;
+            return None;
+        }
+
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Some(bytes)
+    }
+
+    /// Reads bytes preceded by their length as an `L`.
+    pub fn prefixed_bytes<L>(&mut self) -> Option<&'a [u8]>
+    where
+        L: PayloadValue + FrameLen,
+    {
+        let start = self.pos;
+        let len = self.get::<L>()?.to_usize();
+
+        match self.bytes(len) {
+            Some(bytes) => Some(bytes),
+            None => {
+                self.pos = start;
+                None
+            }
+        }
+    }
+
+    /// Reads a string preceded by its length in bytes as an `L`. Returns None without marking
+    /// the parser as overrun if the string is not valid UTF-8.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::payload::PayloadParser;
+    /// let mut parser = PayloadParser::new(&[2, b'h', b'i', 5, b'?']);
+    /// assert_eq!(parser.prefixed_str::<u8>(), Some("hi"));
+    /// assert_eq!(parser.prefixed_str::<u8>(), None);
+    /// assert!(parser.has_overrun());
+    /// ```
+    pub fn prefixed_str<L>(&mut self) -> Option<&'a str>
+    where
+        L: PayloadValue + FrameLen,
+    {
+        let start = self.pos;
+        let bytes = self.prefixed_bytes::<L>()?;
+        self.utf8_at(start, bytes)
+    }
+
+    /// Reads a string terminated by a null byte. Returns None without marking the parser as
+    /// overrun if the string is not valid UTF-8.
+    pub fn nul_str(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        let len = match self.data[self.pos..].iter().position(|b| *b == 0) {
+            Some(len) => len,
+            None => {
+                self.overrun = true;
+                return None;
+            }
+        };
+
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len + 1;
+        self.utf8_at(start, bytes)
+    }
+
+    fn utf8_at(&mut self, start: usize, bytes: &'a [u8]) -> Option<&'a str> {
+        match core::str::from_utf8(bytes) {
+            Ok(string) => Some(string),
+            Err(_) => {
+                self.pos = start;
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! A sans-IO TinyFrame peer.
+//!
+//! [Peer] combines a [MsgEncoder], a [MsgDecoder] and the bookkeeping of pending queries and the
+//! parser timeout, but performs no I/O and never reads a clock. The application feeds it
+//! received bytes with [handle_input](Peer::handle_input) and the current time with
+//! [handle_timeout](Peer::handle_timeout), and takes frames to transmit from
+//! [poll_transmit](Peer::poll_transmit) and received messages from
+//! [poll_event](Peer::poll_event). This makes it usable from any event loop, from an interrupt
+//! handler to an async runtime or a simulation.
+//!
+//! Time is measured in ticks of any unit. Timeouts passed to the peer are relative to the time
+//! of the last [handle_timeout](Peer::handle_timeout) call, so it should be called whenever
+//! [next_timeout](Peer::next_timeout) is reached, and ideally also before sending queries.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use tiny_frame::peer::{Event, Peer};
+//!
+//! let mut master = Peer::<DefaultFormat>::new(true);
+//! let mut slave = Peer::<DefaultFormat>::new(false);
+//!
+//! let query = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 0x70,
+//!     data: b"ping".to_vec(),
+//! };
+//! let id = master.query(query, 100).unwrap();
+//!
+//! while let Some(frame) = master.poll_transmit() {
+//!     slave.handle_input(&frame);
+//! }
+//!
+//! match slave.poll_event() {
+//!     Some(Event::Message(msg)) => {
+//!         slave.send(msg.create_response(0x71, b"pong".to_vec())).unwrap();
+//!     }
+//!     event => panic!("unexpected event {:?}", event),
+//! }
+//!
+//! while let Some(frame) = slave.poll_transmit() {
+//!     master.handle_input(&frame);
+//! }
+//!
+//! match master.poll_event() {
+//!     Some(Event::Response(msg)) => {
+//!         assert_eq!(msg.id, id);
+//!         assert_eq!(msg.data, b"pong");
+//!     }
+//!     event => panic!("unexpected event {:?}", event),
+//! }
+//!
+//! // a query that is not answered times out
+//! let query = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 0x70,
+//!     data: vec![],
+//! };
+//! let id = master.query(query, 100).unwrap();
+//! assert_eq!(master.next_timeout(), Some(100));
+//! master.handle_timeout(100);
+//! assert_eq!(master.poll_event(), Some(Event::QueryTimeout(id)));
+//! ```
+
+use crate::{DecodeError, FrameFormat, Msg, MsgDecoder, MsgEncoder};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+
+/// Default number of ticks after which a partially received frame is discarded, as in upstream
+/// TinyFrame.
+pub const DEFAULT_PARSER_TIMEOUT: u64 = 10;
+
+/// An event produced by a [Peer].
+pub enum Event<F: FrameFormat> {
+    /// A message was received.
+    Message(Msg<F>),
+
+    /// A response to a pending query was received.
+    Response(Msg<F>),
+
+    /// A query with the given ID did not receive a response in time.
+    QueryTimeout(F::Id),
+
+    /// A frame was received, but could not be decoded.
+    DecodeError(DecodeError<F>),
+}
+
+impl<F: FrameFormat> fmt::Debug for Event<F>
+where
+    F::Type: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Message(msg) => f.debug_tuple("Message").field(msg).finish(),
+            Event::Response(msg) => f.debug_tuple("Response").field(msg).finish(),
+            Event::QueryTimeout(id) => f.debug_tuple("QueryTimeout").field(id).finish(),
+            Event::DecodeError(err) => f.debug_tuple("DecodeError").field(err).finish(),
+        }
+    }
+}
+
+impl<F: FrameFormat> Clone for Event<F> {
+    fn clone(&self) -> Event<F> {
+        match self {
+            Event::Message(msg) => Event::Message(msg.clone()),
+            Event::Response(msg) => Event::Response(msg.clone()),
+            Event::QueryTimeout(id) => Event::QueryTimeout(*id),
+            Event::DecodeError(err) => Event::DecodeError(err.clone()),
+        }
+    }
+}
+
+impl<F: FrameFormat> PartialEq for Event<F> {
+    fn eq(&self, other: &Event<F>) -> bool {
+        match (self, other) {
+            (Event::Message(a), Event::Message(b)) | (Event::Response(a), Event::Response(b)) => {
+                a == b
+            }
+            (Event::QueryTimeout(a), Event::QueryTimeout(b)) => a == b,
+            (Event::DecodeError(a), Event::DecodeError(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// A TinyFrame peer that performs no I/O.
+pub struct Peer<F: FrameFormat> {
+    encoder: MsgEncoder<F>,
+    decoder: MsgDecoder<F>,
+    now: u64,
+    parser_timeout: u64,
+    parser_deadline: Option<u64>,
+    queries: Vec<(F::Id, u64)>,
+    transmit: VecDeque<Vec<u8>>,
+    events: VecDeque<Event<F>>,
+}
+
+impl<F: FrameFormat> Peer<F> {
+    /// Creates a new peer.
+    pub fn new(is_master: bool) -> Peer<F> {
+        let mut encoder = MsgEncoder::new();
+        encoder.is_master = is_master;
+
+        Peer {
+            encoder,
+            decoder: MsgDecoder::new(),
+            now: 0,
+            parser_timeout: DEFAULT_PARSER_TIMEOUT,
+            parser_deadline: None,
+            queries: Vec::new(),
+            transmit: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Sets the number of ticks after which a partially received frame is discarded.
+    pub fn set_parser_timeout(&mut self, timeout: u64) {
+        self.parser_timeout = timeout;
+    }
+
+    /// Returns the encoder.
+    pub fn encoder(&mut self) -> &mut MsgEncoder<F> {
+        &mut self.encoder
+    }
+
+    /// Handles received bytes.
+    pub fn handle_input(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            // the parser timeout only applies while a frame is incomplete
+            self.parser_deadline = Some(self.now.saturating_add(self.parser_timeout));
+
+            let msg = match self.decoder.try_accept(byte) {
+                Some(Ok(msg)) => msg,
+                Some(Err(err)) => {
+                    self.parser_deadline = None;
+                    self.events.push_back(Event::DecodeError(err));
+                    continue;
+                }
+                None => continue,
+            };
+            self.parser_deadline = None;
+
+            match self.queries.iter().position(|&(id, _)| id == msg.id) {
+                Some(i) => {
+                    self.queries.remove(i);
+                    self.events.push_back(Event::Response(msg));
+                }
+                None => self.events.push_back(Event::Message(msg)),
+            }
+        }
+    }
+
+    /// Queues a message for transmission. If the message is not a response, a new ID will be
+    /// assigned, skipping the IDs of pending queries. Returns the ID of the message.
+    pub fn send(&mut self, mut msg: Msg<F>) -> io::Result<F::Id> {
+        if !msg.is_response {
+            msg.id = self.free_id()?;
+            // sent as a response so that the encoder keeps the ID
+            msg.is_response = true;
+        }
+
+        let mut frame = Vec::new();
+        let id = msg.encode(&mut frame, &mut self.encoder)?;
+        self.transmit.push_back(frame);
+        Ok(id)
+    }
+
+    /// Queues a message for transmission and waits for a response with the same ID. If no
+    /// response arrives within `timeout` ticks, a [QueryTimeout](Event::QueryTimeout) event is
+    /// produced. Returns the ID of the message.
+    ///
+    /// As with [send](Self::send), new IDs skip the IDs of pending queries, so that a response
+    /// can only complete the query it answers. Fails if all IDs are in use, or if the message is
+    /// a response with the ID of a pending query.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::*;
+    /// use tiny_frame::peer::Peer;
+    ///
+    /// let mut peer = Peer::<DefaultFormat>::new(false);
+    /// let ping = || Msg {
+    ///     id: 0,
+    ///     is_response: false,
+    ///     msg_type: 0x70,
+    ///     data: vec![],
+    /// };
+    ///
+    /// // a u8 ID leaves 128 IDs per peer
+    /// for id in 0..128 {
+    ///     assert_eq!(peer.query(ping(), 100).unwrap(), id);
+    /// }
+    /// assert!(peer.query(ping(), 100).is_err());
+    ///
+    /// // the IDs wrap around to the first one that is free
+    /// peer.cancel_query(5);
+    /// assert_eq!(peer.query(ping(), 100).unwrap(), 5);
+    /// ```
+    pub fn query(&mut self, msg: Msg<F>, timeout: u64) -> io::Result<F::Id> {
+        if msg.is_response && self.queries.iter().any(|&(id, _)| id == msg.id) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "query ID is already in use",
+            ));
+        }
+
+        let id = self.send(msg)?;
+        self.queries.push((id, self.now.saturating_add(timeout)));
+        Ok(id)
+    }
+
+    /// Returns the next ID that does not belong to a pending query.
+    fn free_id(&mut self) -> io::Result<F::Id> {
+        // of this many consecutive IDs, at least one is free unless the ID space is exhausted
+        for _ in 0..=self.queries.len() {
+            let id = self.encoder.next_id();
+            if self.queries.iter().all(|&(query, _)| query != id) {
+                return Ok(id);
+            }
+        }
+        Err(io::Error::other("all query IDs are in use"))
+    }
+
+    /// Stops waiting for a response to a query. Returns false if there is no pending query with
+    /// the given ID.
+    pub fn cancel_query(&mut self, id: F::Id) -> bool {
+        match self.queries.iter().position(|&(query, _)| query == id) {
+            Some(i) => {
+                self.queries.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the next frame to transmit.
+    pub fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        self.transmit.pop_front()
+    }
+
+    /// Returns the next event.
+    pub fn poll_event(&mut self) -> Option<Event<F>> {
+        self.events.pop_front()
+    }
+
+    /// Advances the time to `now`, expiring queries and partially received frames.
+    pub fn handle_timeout(&mut self, now: u64) {
+        self.now = self.now.max(now);
+
+        if self
+            .parser_deadline
+            .is_some_and(|deadline| deadline <= self.now)
+        {
+            self.decoder.reset();
+            self.parser_deadline = None;
+        }
+
+        let now = self.now;
+        let events = &mut self.events;
+        self.queries.retain(|&(id, deadline)| {
+            if deadline <= now {
+                events.push_back(Event::QueryTimeout(id));
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Returns the time at which [handle_timeout](Self::handle_timeout) should be called next.
+    pub fn next_timeout(&self) -> Option<u64> {
+        self.queries
+            .iter()
+            .map(|&(_, deadline)| deadline)
+            .chain(self.parser_deadline)
+            .min()
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for Peer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Peer")
+            .field("encoder", &self.encoder)
+            .field("now", &self.now)
+            .field("queries", &self.queries.len())
+            .field("transmit", &self.transmit.len())
+            .field("events", &self.events.len())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,374 @@
+//! Queries as futures.
+//!
+//! [AsyncPeer] wraps a [Peer] so that queries can be awaited: [query](AsyncPeer::query) sends a
+//! message and returns a [Query] future that resolves to the response with the same ID, or to a
+//! [TimedOut](io::ErrorKind::TimedOut) error. Any number of queries can be in flight at once, and
+//! dropping a [Query] cancels it.
+//!
+//! IDs that belong to a query which has not been dropped yet, including one that has finished
+//! but was not polled, are skipped when assigning IDs to new queries, so that a response can only
+//! complete the query it answers. If all IDs are in use, [query](AsyncPeer::query) fails.
+//!
+//! No executor, reactor or timer is assumed. As with [Peer], a driver (a task, a thread or an
+//! interrupt handler) passes received bytes to [handle_input](AsyncPeer::handle_input), the
+//! current time to [handle_timeout](AsyncPeer::handle_timeout), and writes the frames returned by
+//! [poll_transmit](AsyncPeer::poll_transmit) to the transport. [AsyncPeer] can be cloned and
+//! shared between the driver and any number of tasks.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use futures_executor::block_on;
+//! use std::io;
+//! use tiny_frame::peer::{Event, Peer};
+//! use tiny_frame::query::AsyncPeer;
+//!
+//! let master = AsyncPeer::<DefaultFormat>::new(Peer::new(true));
+//! let mut slave = Peer::<DefaultFormat>::new(false);
+//!
+//! let ping = |data: &[u8]| Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 0x70,
+//!     data: data.to_vec(),
+//! };
+//! let first = master.query(ping(b"first"));
+//! let second = master.query(ping(b"second"));
+//!
+//! // the slave only answers the first query
+//! while let Some(frame) = master.take_transmit() {
+//!     slave.handle_input(&frame);
+//! }
+//! while let Some(event) = slave.poll_event() {
+//!     if let Event::Message(msg) = event {
+//!         if msg.data == b"first" {
+//!             slave.send(msg.create_response(0x71, b"pong".to_vec())).unwrap();
+//!         }
+//!     }
+//! }
+//! while let Some(frame) = slave.poll_transmit() {
+//!     master.handle_input(&frame);
+//! }
+//! master.handle_timeout(master.next_timeout().unwrap());
+//!
+//! block_on(async {
+//!     let reply = first.await?;
+//!     assert_eq!(reply.data, b"pong");
+//!
+//!     let err = second.await.unwrap_err();
+//!     assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+//!     Ok::<(), io::Error>(())
+//! })
+//! .unwrap();
+//! ```
+
+use crate::peer::{Event, Peer};
+use crate::{FrameFormat, Msg};
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+/// Default number of ticks after which a query times out.
+pub const DEFAULT_QUERY_TIMEOUT: u64 = 1000;
+
+/// A [Peer] whose queries are futures.
+pub struct AsyncPeer<F: FrameFormat> {
+    inner: Arc<Mutex<Inner<F>>>,
+}
+
+struct Inner<F: FrameFormat> {
+    peer: Peer<F>,
+    query_timeout: u64,
+    queries: Vec<QuerySlot<F>>,
+    next_token: u64,
+    events: VecDeque<Event<F>>,
+    transmit_waker: Option<Waker>,
+}
+
+struct QuerySlot<F: FrameFormat> {
+    /// Identifies the [Query] of this slot, as IDs are reused.
+    token: u64,
+    id: F::Id,
+    slot: Slot<F>,
+}
+
+enum Slot<F: FrameFormat> {
+    Waiting(Option<Waker>),
+    Done(io::Result<Msg<F>>),
+}
+
+impl<F: FrameFormat> Inner<F> {
+    /// Moves events from the peer to the queries they complete, or to the event queue.
+    fn dispatch(&mut self) {
+        while let Some(event) = self.peer.poll_event() {
+            let (id, result) = match event {
+                Event::Response(msg) => (msg.id, Ok(msg)),
+                Event::QueryTimeout(id) => (
+                    id,
+                    Err(io::Error::new(io::ErrorKind::TimedOut, "query timed out")),
+                ),
+                event => {
+                    self.events.push_back(event);
+                    continue;
+                }
+            };
+
+            let slot = self
+                .queries
+                .iter_mut()
+                .find(|query| query.id == id && matches!(query.slot, Slot::Waiting(_)));
+            if let Some(query) = slot {
+                if let Slot::Waiting(Some(waker)) = &query.slot {
+                    waker.wake_by_ref();
+                }
+                query.slot = Slot::Done(result);
+            }
+        }
+    }
+
+    /// Returns the next ID that does not belong to a query. Returns None if all IDs are in use.
+    fn free_id(&mut self) -> Option<F::Id> {
+        // of this many consecutive IDs, at least one is free unless the ID space is exhausted
+        for _ in 0..=self.queries.len() {
+            let id = self.peer.encoder().next_id();
+            if self.queries.iter().all(|query| query.id != id) {
+                return Some(id);
+            }
+        }
+        None
+    }
+
+    fn wake_transmit(&mut self) {
+        if let Some(waker) = self.transmit_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<F: FrameFormat> AsyncPeer<F> {
+    /// Wraps a peer.
+    pub fn new(peer: Peer<F>) -> AsyncPeer<F> {
+        AsyncPeer {
+            inner: Arc::new(Mutex::new(Inner {
+                peer,
+                query_timeout: DEFAULT_QUERY_TIMEOUT,
+                queries: Vec::new(),
+                next_token: 0,
+                events: VecDeque::new(),
+                transmit_waker: None,
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner<F>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Sets the number of ticks after which queries time out.
+    pub fn set_query_timeout(&self, timeout: u64) {
+        self.lock().query_timeout = timeout;
+    }
+
+    /// Queues a message for transmission without waiting for a response. If the message is not
+    /// a response, a new ID will be assigned. Returns the ID of the message.
+    pub fn send(&self, msg: Msg<F>) -> io::Result<F::Id> {
+        let mut inner = self.lock();
+        let id = inner.peer.send(msg)?;
+        inner.wake_transmit();
+        Ok(id)
+    }
+
+    /// Queues a message for transmission and returns a future that resolves to the response
+    /// with the same ID. A new ID is assigned to the message; the future fails if all IDs are
+    /// in use by other queries.
+    pub fn query(&self, mut msg: Msg<F>) -> Query<F> {
+        let mut inner = self.lock();
+
+        let state = match inner.free_id() {
+            Some(id) => {
+                // sent as a response so that the encoder keeps the ID
+                msg.id = id;
+                msg.is_response = true;
+
+                let timeout = inner.query_timeout;
+                match inner.peer.query(msg, timeout) {
+                    Ok(id) => {
+                        let token = inner.next_token;
+                        inner.next_token += 1;
+                        inner.queries.push(QuerySlot {
+                            token,
+                            id,
+                            slot: Slot::Waiting(None),
+                        });
+                        inner.wake_transmit();
+                        QueryState::Pending(id, token)
+                    }
+                    Err(err) => QueryState::Failed(Some(err)),
+                }
+            }
+            None => QueryState::Failed(Some(io::Error::other("all query IDs are in use"))),
+        };
+
+        Query {
+            inner: Arc::clone(&self.inner),
+            state,
+        }
+    }
+
+    /// Handles received bytes, completing the queries they answer.
+    pub fn handle_input(&self, bytes: &[u8]) {
+        let mut inner = self.lock();
+        inner.peer.handle_input(bytes);
+        inner.dispatch();
+    }
+
+    /// Advances the time to `now`, failing queries that timed out.
+    pub fn handle_timeout(&self, now: u64) {
+        let mut inner = self.lock();
+        inner.peer.handle_timeout(now);
+        inner.dispatch();
+    }
+
+    /// Returns the time at which [handle_timeout](Self::handle_timeout) should be called next.
+    pub fn next_timeout(&self) -> Option<u64> {
+        self.lock().peer.next_timeout()
+    }
+
+    /// Returns the next frame to transmit, if any.
+    pub fn take_transmit(&self) -> Option<Vec<u8>> {
+        self.lock().peer.poll_transmit()
+    }
+
+    /// Returns the next frame to transmit, or registers the current task to be woken when one is
+    /// queued.
+    pub fn poll_transmit(&self, cx: &mut Context<'_>) -> Poll<Vec<u8>> {
+        let mut inner = self.lock();
+        match inner.peer.poll_transmit() {
+            Some(frame) => Poll::Ready(frame),
+            None => {
+                inner.transmit_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Returns the next received message that is not a response to a query, or a decoding
+    /// error.
+    pub fn poll_event(&self) -> Option<Event<F>> {
+        self.lock().events.pop_front()
+    }
+}
+
+impl<F: FrameFormat> Clone for AsyncPeer<F> {
+    fn clone(&self) -> AsyncPeer<F> {
+        AsyncPeer {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for AsyncPeer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let inner = self.lock();
+        f.debug_struct("AsyncPeer")
+            .field("peer", &inner.peer)
+            .field("queries", &inner.queries.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A future that resolves to the response to a query.
+///
+/// Created by [AsyncPeer::query]. Dropping it cancels the query.
+#[must_use = "futures do nothing unless polled"]
+pub struct Query<F: FrameFormat> {
+    inner: Arc<Mutex<Inner<F>>>,
+    state: QueryState<F>,
+}
+
+enum QueryState<F: FrameFormat> {
+    Pending(F::Id, u64),
+    Failed(Option<io::Error>),
+    Finished,
+}
+
+impl<F: FrameFormat> Query<F> {
+    /// Returns the ID of the query, unless sending it failed.
+    pub fn id(&self) -> Option<F::Id> {
+        match self.state {
+            QueryState::Pending(id, _) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+// Query holds no pinned data
+impl<F: FrameFormat> Unpin for Query<F> {}
+
+impl<F: FrameFormat> Future for Query<F> {
+    type Output = io::Result<Msg<F>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<Msg<F>>> {
+        let this = self.get_mut();
+        let token = match &mut this.state {
+            QueryState::Pending(_, token) => *token,
+            QueryState::Failed(err) => {
+                let err = err.take().expect("query polled after completion");
+                this.state = QueryState::Finished;
+                return Poll::Ready(Err(err));
+            }
+            QueryState::Finished => panic!("query polled after completion"),
+        };
+
+        let mut inner = this.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let i = inner
+            .queries
+            .iter()
+            .position(|query| query.token == token)
+            .expect("pending query has a slot");
+
+        match &mut inner.queries[i].slot {
+            Slot::Waiting(waker) => {
+                *waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            Slot::Done(_) => {
+                let result = match inner.queries.remove(i).slot {
+                    Slot::Done(result) => result,
+                    Slot::Waiting(_) => unreachable!(),
+                };
+                drop(inner);
+                this.state = QueryState::Finished;
+                Poll::Ready(result)
+            }
+        }
+    }
+}
+
+impl<F: FrameFormat> Drop for Query<F> {
+    fn drop(&mut self) {
+        if let QueryState::Pending(id, token) = self.state {
+            let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+            let i = inner
+                .queries
+                .iter()
+                .position(|query| query.token == token)
+                .expect("pending query has a slot");
+
+            // the peer only waits for queries that have not finished
+            if let Slot::Waiting(_) = inner.queries.remove(i).slot {
+                inner.peer.cancel_query(id);
+            }
+        }
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for Query<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Query").field("id", &self.id()).finish()
+    }
+}
@@ -0,0 +1,355 @@
+//! Acknowledged delivery with retransmission.
+//!
+//! [Msg::encode] is fire-and-forget. [ReliableLink] wraps a [MsgEncoder] and keeps a copy of
+//! every frame it sends until the peer acknowledges it, retransmitting it when no
+//! acknowledgement arrives in time.
+//!
+//! An acknowledgement is a response with the ID of the acknowledged frame, a message type chosen
+//! by the application ([ReliableLink::new]) and no payload. Both peers must use the same
+//! acknowledgement type. Received frames are acknowledged even if they are duplicates, since the
+//! previous acknowledgement may have been lost, but duplicates are only passed to the
+//! application once.
+//!
+//! Like upstream TinyFrame, this module does not read a clock. Timeouts are measured in ticks
+//! of any unit, and the current time is passed to every call that needs it.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use tiny_frame::reliable::{Delivery, ReliableLink, RetryConfig};
+//! use std::sync::{Arc, Mutex};
+//!
+//! const ACK: u8 = 0xff;
+//!
+//! let config = RetryConfig::default();
+//! let mut master = ReliableLink::<DefaultFormat>::new(MsgEncoder::new(), ACK, config);
+//! master.encoder().is_master = true;
+//! let mut slave = ReliableLink::<DefaultFormat>::new(MsgEncoder::new(), ACK, config);
+//!
+//! let delivered = Arc::new(Mutex::new(Vec::new()));
+//! let delivered2 = Arc::clone(&delivered);
+//! master.set_delivery_callback(move |id, delivery| {
+//!     delivered2.lock().unwrap().push((id, delivery))
+//! });
+//!
+//! let msg = Msg {
+//!     id: 0,
+//!     is_response: false,
+//!     msg_type: 0x10,
+//!     data: b"hello".to_vec(),
+//! };
+//!
+//! // the first transmission is lost
+//! let id = master.send(msg, &mut Vec::new(), 0).unwrap();
+//!
+//! // after the timeout, the frame is sent again
+//! let mut wire = Vec::new();
+//! master.tick(RetryConfig::default().timeout, &mut wire).unwrap();
+//!
+//! // the slave receives it and sends an acknowledgement
+//! let mut decoder = MsgDecoder::<DefaultFormat>::new();
+//! let received = wire.iter().find_map(|&b| decoder.accept(b)).unwrap();
+//! let mut ack = Vec::new();
+//! let received = slave.receive(received, &mut ack, 100).unwrap();
+//! assert_eq!(received.unwrap().data, b"hello");
+//!
+//! // the master receives the acknowledgement
+//! let ack = ack.iter().find_map(|&b| decoder.accept(b)).unwrap();
+//! assert_eq!(master.receive(ack, &mut Vec::new(), 110).unwrap(), None);
+//! assert_eq!(*delivered.lock().unwrap(), [(id, Delivery::Delivered)]);
+//! ```
+
+use crate::{FrameFormat, Msg, MsgEncoder};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Retransmission parameters. All durations are in ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetryConfig {
+    /// Time to wait for an acknowledgement before the first retransmission.
+    pub timeout: u64,
+
+    /// Number of retransmissions before a frame is reported as failed.
+    pub retries: u32,
+
+    /// Factor by which the timeout grows after every retransmission.
+    pub backoff: u32,
+
+    /// Upper limit for the timeout after backoff.
+    pub max_timeout: u64,
+
+    /// How long the IDs of received frames are remembered to suppress duplicates. This should
+    /// be longer than the total retransmission time, but shorter than the time it takes the
+    /// peer to reuse an ID.
+    pub dedup_window: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> RetryConfig {
+        RetryConfig {
+            timeout: 100,
+            retries: 3,
+            backoff: 2,
+            max_timeout: 1000,
+            dedup_window: 2000,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Returns the timeout after the given number of retransmissions.
+    fn timeout_after(&self, retransmissions: u32) -> u64 {
+        let factor = (self.backoff.max(1) as u64).saturating_pow(retransmissions);
+        self.timeout
+            .saturating_mul(factor)
+            .min(self.max_timeout.max(self.timeout))
+    }
+}
+
+/// The outcome of a reliable transmission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Delivery {
+    /// The peer acknowledged the frame.
+    Delivered,
+
+    /// The frame was not acknowledged after all retransmissions.
+    Failed,
+}
+
+/// A delivery callback, called with the ID of the frame and its outcome.
+pub type DeliveryCallback<F> = Box<dyn FnMut(<F as FrameFormat>::Id, Delivery) + Send>;
+
+struct Pending<F: FrameFormat> {
+    id: F::Id,
+    frame: Vec<u8>,
+    retransmissions: u32,
+    deadline: u64,
+}
+
+/// A message sender and receiver with acknowledgements, retransmission and duplicate
+/// suppression.
+pub struct ReliableLink<F: FrameFormat> {
+    encoder: MsgEncoder<F>,
+    ack_type: F::Type,
+    config: RetryConfig,
+    pending: Vec<Pending<F>>,
+    received: VecDeque<(F::Id, u64)>,
+    on_delivery: Option<DeliveryCallback<F>>,
+}
+
+impl<F: FrameFormat> ReliableLink<F> {
+    /// Creates a new link. `ack_type` is the message type used for acknowledgements, and must
+    /// not be used for any other message.
+    pub fn new(encoder: MsgEncoder<F>, ack_type: F::Type, config: RetryConfig) -> ReliableLink<F> {
+        ReliableLink {
+            encoder,
+            ack_type,
+            config,
+            pending: Vec::new(),
+            received: VecDeque::new(),
+            on_delivery: None,
+        }
+    }
+
+    /// Sets the function called when a frame is acknowledged or has failed.
+    pub fn set_delivery_callback<C>(&mut self, callback: C)
+    where
+        C: FnMut(F::Id, Delivery) + Send + 'static,
+    {
+        self.on_delivery = Some(Box::new(callback));
+    }
+
+    /// Returns the encoder.
+    pub fn encoder(&mut self) -> &mut MsgEncoder<F> {
+        &mut self.encoder
+    }
+
+    /// Returns the retransmission parameters.
+    pub fn config(&self) -> &RetryConfig {
+        &self.config
+    }
+
+    /// Returns the number of frames waiting for an acknowledgement.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns the time at which [tick](Self::tick) next needs to be called, if any frames are
+    /// waiting for an acknowledgement.
+    pub fn next_deadline(&self) -> Option<u64> {
+        self.pending.iter().map(|pending| pending.deadline).min()
+    }
+
+    /// Encodes a message, writes it to the output and keeps it for retransmission. If the
+    /// message is not a response, a new ID will be assigned by the encoder. Returns the ID of
+    /// the sent message.
+    pub fn send<W: Write>(&mut self, msg: Msg<F>, out: &mut W, now: u64) -> io::Result<F::Id> {
+        let mut frame = Vec::new();
+        let id = msg.encode(&mut frame, &mut self.encoder)?;
+        out.write_all(&frame)?;
+
+        self.pending.push(Pending {
+            id,
+            frame,
+            retransmissions: 0,
+            deadline: now.saturating_add(self.config.timeout),
+        });
+        Ok(id)
+    }
+
+    /// Handles a received message.
+    ///
+    /// Acknowledgements are consumed and reported to the delivery callback. Other messages are
+    /// acknowledged by writing to the output, and returned unless they are duplicates.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::*;
+    /// use tiny_frame::reliable::{ReliableLink, RetryConfig};
+    ///
+    /// let config = RetryConfig {
+    ///     dedup_window: 500,
+    ///     ..RetryConfig::default()
+    /// };
+    /// let mut link = ReliableLink::<DefaultFormat>::new(MsgEncoder::new(), 0xff, config);
+    /// let msg = Msg {
+    ///     id: 0x83,
+    ///     is_response: false,
+    ///     msg_type: 0x10,
+    ///     data: b"hello".to_vec(),
+    /// };
+    ///
+    /// let mut acks = Vec::new();
+    /// assert_eq!(link.receive(msg.clone(), &mut acks, 0).unwrap(), Some(msg.clone()));
+    ///
+    /// // a retransmission is acknowledged again, but not returned
+    /// let mut second_ack = Vec::new();
+    /// assert_eq!(link.receive(msg.clone(), &mut second_ack, 100).unwrap(), None);
+    /// assert_eq!(second_ack, acks);
+    ///
+    /// // once the window has passed, the ID counts as a new message
+    /// assert_eq!(link.receive(msg.clone(), &mut Vec::new(), 500).unwrap(), Some(msg));
+    /// ```
+    pub fn receive<W: Write>(
+        &mut self,
+        msg: Msg<F>,
+        out: &mut W,
+        now: u64,
+    ) -> io::Result<Option<Msg<F>>> {
+        if msg.msg_type == self.ack_type && msg.data.is_empty() {
+            if let Some(i) = self.pending.iter().position(|pending| pending.id == msg.id) {
+                self.pending.remove(i);
+                self.report(msg.id, Delivery::Delivered);
+            }
+            return Ok(None);
+        }
+
+        msg.create_response(self.ack_type, Vec::new())
+            .encode(out, &mut self.encoder)?;
+
+        while let Some(&(_, expires)) = self.received.front() {
+            if expires > now {
+                break;
+            }
+            self.received.pop_front();
+        }
+
+        if self.received.iter().any(|&(id, _)| id == msg.id) {
+            return Ok(None);
+        }
+        self.received
+            .push_back((msg.id, now.saturating_add(self.config.dedup_window)));
+
+        Ok(Some(msg))
+    }
+
+    /// Retransmits frames whose acknowledgement timed out, and reports frames that have run out
+    /// of retransmissions as failed.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::*;
+    /// use std::sync::{Arc, Mutex};
+    /// use tiny_frame::reliable::{Delivery, ReliableLink, RetryConfig};
+    ///
+    /// let config = RetryConfig {
+    ///     timeout: 10,
+    ///     retries: 2,
+    ///     backoff: 2,
+    ///     ..RetryConfig::default()
+    /// };
+    /// let mut link = ReliableLink::<DefaultFormat>::new(MsgEncoder::new(), 0xff, config);
+    /// let outcomes = Arc::new(Mutex::new(Vec::new()));
+    /// let outcomes2 = Arc::clone(&outcomes);
+    /// link.set_delivery_callback(move |id, delivery| {
+    ///     outcomes2.lock().unwrap().push((id, delivery))
+    /// });
+    ///
+    /// let msg = Msg {
+    ///     id: 0,
+    ///     is_response: false,
+    ///     msg_type: 0x10,
+    ///     data: b"hello".to_vec(),
+    /// };
+    /// let mut wire = Vec::new();
+    /// let id = link.send(msg, &mut wire, 0).unwrap();
+    /// let frame_len = wire.len();
+    ///
+    /// // retransmitted after 10 ticks, and after 20 more
+    /// link.tick(9, &mut wire).unwrap();
+    /// assert_eq!(wire.len(), frame_len);
+    /// link.tick(10, &mut wire).unwrap();
+    /// assert_eq!(link.next_deadline(), Some(30));
+    /// link.tick(30, &mut wire).unwrap();
+    /// assert_eq!(wire.len(), 3 * frame_len);
+    ///
+    /// // without an acknowledgement after the last retransmission, the frame has failed
+    /// link.tick(69, &mut wire).unwrap();
+    /// assert!(outcomes.lock().unwrap().is_empty());
+    /// link.tick(70, &mut wire).unwrap();
+    /// assert_eq!(wire.len(), 3 * frame_len);
+    /// assert_eq!(link.pending(), 0);
+    /// assert_eq!(*outcomes.lock().unwrap(), [(id, Delivery::Failed)]);
+    /// ```
+    pub fn tick<W: Write>(&mut self, now: u64, out: &mut W) -> io::Result<()> {
+        let mut i = 0;
+        while i < self.pending.len() {
+            let pending = &mut self.pending[i];
+            if pending.deadline > now {
+                i += 1;
+                continue;
+            }
+
+            if pending.retransmissions >= self.config.retries {
+                let id = pending.id;
+                self.pending.remove(i);
+                self.report(id, Delivery::Failed);
+                continue;
+            }
+
+            pending.retransmissions += 1;
+            pending.deadline =
+                now.saturating_add(self.config.timeout_after(pending.retransmissions));
+            out.write_all(&pending.frame)?;
+            i += 1;
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, id: F::Id, delivery: Delivery) {
+        if let Some(callback) = &mut self.on_delivery {
+            callback(id, delivery);
+        }
+    }
+}
+
+impl<F: FrameFormat> fmt::Debug for ReliableLink<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReliableLink")
+            .field("encoder", &self.encoder)
+            .field("config", &self.config)
+            .field("pending", &self.pending.len())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,205 @@
+//! Serial port transport.
+//!
+//! This module is only available with the `serial` feature. [SerialTransport] opens a serial
+//! device (RS-232, USB-CDC and the like) with the given [SerialConfig] and sends and receives
+//! messages over it. Received messages are read with [recv](SerialTransport::recv) or handled
+//! in a loop with [run](SerialTransport::run); messages can also be sent from other threads
+//! through a [MsgSender] returned by [sender](SerialTransport::sender).
+//!
+//! # Examples
+//! ```no_run
+//! # use tiny_frame::*;
+//! use tiny_frame::serial::{Parity, SerialConfig, SerialTransport};
+//!
+//! let config = SerialConfig {
+//!     baud_rate: 9600,
+//!     parity: Parity::Even,
+//!     ..SerialConfig::default()
+//! };
+//! let mut transport =
+//!     SerialTransport::<DefaultFormat>::open("/dev/ttyUSB0", &config, MsgEncoder::new())?;
+//!
+//! // answer every message with its payload
+//! transport.run(|msg| Some(msg.create_response(msg.msg_type, msg.data.clone())))?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::sender::MsgSender;
+use crate::{FrameFormat, Msg, MsgDecoder, MsgEncoder};
+use serialport::SerialPort;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+pub use serialport::{DataBits, Parity, StopBits};
+
+const READ_BUFFER_SIZE: usize = 256;
+
+/// Serial port settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialConfig {
+    /// The baud rate.
+    pub baud_rate: u32,
+
+    /// The number of data bits per character.
+    pub data_bits: DataBits,
+
+    /// The parity checking mode.
+    pub parity: Parity,
+
+    /// The number of stop bits.
+    pub stop_bits: StopBits,
+
+    /// The read timeout. A partially received frame is discarded when no byte arrives within
+    /// this time, like the parser timeout of upstream TinyFrame.
+    pub timeout: Duration,
+}
+
+impl Default for SerialConfig {
+    /// 115200 baud, 8 data bits, no parity, 1 stop bit and a 100 ms timeout.
+    fn default() -> SerialConfig {
+        SerialConfig {
+            baud_rate: 115_200,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            timeout: Duration::from_millis(100),
+        }
+    }
+}
+
+/// A TinyFrame connection over a serial port.
+pub struct SerialTransport<F: FrameFormat> {
+    port: Box<dyn SerialPort>,
+    decoder: MsgDecoder<F>,
+    sender: MsgSender<F, Box<dyn SerialPort>>,
+    buf: [u8; READ_BUFFER_SIZE],
+    pos: usize,
+    len: usize,
+}
+
+impl<F: FrameFormat> SerialTransport<F> {
+    /// Opens a serial device.
+    pub fn open(
+        path: &str,
+        config: &SerialConfig,
+        encoder: MsgEncoder<F>,
+    ) -> io::Result<SerialTransport<F>> {
+        let port = serialport::new(path, config.baud_rate)
+            .data_bits(config.data_bits)
+            .parity(config.parity)
+            .stop_bits(config.stop_bits)
+            .timeout(config.timeout)
+            .open()?;
+        SerialTransport::new(port, encoder)
+    }
+
+    /// Creates a transport over a serial port that is already open.
+    pub fn new(
+        port: Box<dyn SerialPort>,
+        encoder: MsgEncoder<F>,
+    ) -> io::Result<SerialTransport<F>> {
+        let writer = port.try_clone()?;
+        Ok(SerialTransport {
+            port,
+            decoder: MsgDecoder::new(),
+            sender: MsgSender::new(encoder, writer),
+            buf: [0; READ_BUFFER_SIZE],
+            pos: 0,
+            len: 0,
+        })
+    }
+
+    /// Returns a sender that writes to the port and can be used from other threads.
+    pub fn sender(&self) -> MsgSender<F, Box<dyn SerialPort>> {
+        self.sender.clone()
+    }
+
+    /// Returns the decoder.
+    pub fn decoder(&mut self) -> &mut MsgDecoder<F> {
+        &mut self.decoder
+    }
+
+    /// Returns the serial port, for example to change its settings.
+    pub fn port(&mut self) -> &mut dyn SerialPort {
+        &mut *self.port
+    }
+
+    /// Sends a message. If the message is not a response, a new ID will be assigned. Returns the
+    /// ID of the message.
+    pub fn send(&self, msg: Msg<F>) -> io::Result<F::Id> {
+        self.sender.send(msg)
+    }
+
+    /// Receives the next message. Returns `None` if no complete message arrived before the read
+    /// timeout; a partially received frame is discarded in that case.
+    ///
+    /// Frames that fail to decode are dropped, as with [MsgDecoder::accept]. Fails with
+    /// [UnexpectedEof](io::ErrorKind::UnexpectedEof) once the port is closed or hung up, such as
+    /// when a USB adapter is unplugged or the other side of a pseudo-terminal is closed.
+    pub fn recv(&mut self) -> io::Result<Option<Msg<F>>> {
+        loop {
+            while self.pos < self.len {
+                let byte = self.buf[self.pos];
+                self.pos += 1;
+                if let Some(msg) = self.decoder.accept(byte) {
+                    return Ok(Some(msg));
+                }
+            }
+
+            match self.port.read(&mut self.buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Err(err) if is_hangup(&err) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => {
+                    self.pos = 0;
+                    self.len = len;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    self.decoder.reset();
+                    return Ok(None);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Receives messages and passes them to `handler` until the port is closed or fails. If
+    /// the handler returns a message, it is sent. Returns `Ok` if the port was closed or hung
+    /// up (see [recv](Self::recv)).
+    pub fn run<H>(&mut self, mut handler: H) -> io::Result<()>
+    where
+        H: FnMut(Msg<F>) -> Option<Msg<F>>,
+    {
+        loop {
+            let msg = match self.recv() {
+                Ok(Some(msg)) => msg,
+                Ok(None) => continue,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(err) => return Err(err),
+            };
+
+            if let Some(reply) = handler(msg) {
+                self.send(reply)?;
+            }
+        }
+    }
+}
+
+/// Returns true if a read error means that the port hung up.
+///
+/// serialport reports a hangup as [BrokenPipe](io::ErrorKind::BrokenPipe), while reading the
+/// device itself afterwards fails with `EIO` on Linux and macOS.
+fn is_hangup(err: &io::Error) -> bool {
+    const EIO: i32 = 5;
+    err.kind() == io::ErrorKind::BrokenPipe || (cfg!(unix) && err.raw_os_error() == Some(EIO))
+}
+
+impl<F: FrameFormat> fmt::Debug for SerialTransport<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SerialTransport")
+            .field("port", &self.port.name())
+            .field("buffered", &(self.len - self.pos))
+            .finish_non_exhaustive()
+    }
+}
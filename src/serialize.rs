@@ -0,0 +1,86 @@
+//! Serde payload encoding.
+//!
+//! This module is only available with the `serde` feature. Payloads are encoded using the
+//! compact [postcard](https://docs.rs/postcard) format.
+//!
+//! [Msg] itself also implements `Serialize` and `Deserialize` with this feature, e.g. for logging
+//! received messages:
+//!
+//! ```
+//! # use tiny_frame::*;
+//! let msg: Msg<DefaultFormat> = Msg {
+//!     id: 3,
+//!     is_response: true,
+//!     msg_type: 1,
+//!     data: vec![1, 2, 3],
+//! };
+//! let bytes = postcard::to_allocvec(&msg).unwrap();
+//! assert_eq!(postcard::from_bytes::<Msg<DefaultFormat>>(&bytes).unwrap(), msg);
+//! ```
+
+use crate::{FrameFormat, Msg};
+use serde::{Deserialize, Serialize};
+
+/// Errors returned when serializing or deserializing a payload.
+pub type Error = postcard::Error;
+
+impl<F: FrameFormat> Msg<F> {
+    /// Creates a message with the given value serialized as payload. The ID will be assigned
+    /// when encoding.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::*;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    /// struct Status {
+    ///     name: String,
+    ///     uptime: u32,
+    /// }
+    ///
+    /// let status = Status {
+    ///     name: "pump".to_string(),
+    ///     uptime: 3600,
+    /// };
+    /// let msg: Msg<DefaultFormat> = Msg::from_serializable(4, &status).unwrap();
+    /// assert_eq!(msg.msg_type, 4);
+    ///
+    /// assert_eq!(msg.deserialize::<Status>().unwrap(), status);
+    /// ```
+    pub fn from_serializable<T>(msg_type: F::Type, value: &T) -> Result<Msg<F>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(Msg {
+            id: Default::default(),
+            is_response: false,
+            msg_type,
+            data: postcard::to_allocvec(value)?,
+        })
+    }
+
+    /// Deserializes the payload of this message. The payload must contain exactly one value,
+    /// leftover bytes are reported as [Error::DeserializeBadEncoding].
+    ///
+    /// # Examples
+    /// ```
+    /// # use tiny_frame::*;
+    /// use tiny_frame::serialize::Error;
+    ///
+    /// let mut msg: Msg<DefaultFormat> = Msg::from_serializable(4, &7u8).unwrap();
+    /// assert_eq!(msg.deserialize::<u8>(), Ok(7));
+    ///
+    /// msg.data.push(0);
+    /// assert_eq!(msg.deserialize::<u8>(), Err(Error::DeserializeBadEncoding));
+    /// ```
+    pub fn deserialize<'a, T>(&'a self) -> Result<T, Error>
+    where
+        T: Deserialize<'a>,
+    {
+        match postcard::take_from_bytes(&self.data)? {
+            (value, []) => Ok(value),
+            _ => Err(Error::DeserializeBadEncoding),
+        }
+    }
+}
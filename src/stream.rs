@@ -0,0 +1,249 @@
+//! Asynchronous streams and sinks of messages.
+//!
+//! This module is only available with the `futures` feature. It works with any runtime that
+//! provides [futures-io](futures_io) readers and writers, such as smol or async-std, and with
+//! tokio through its compatibility layer.
+//!
+//! [FrameStream] reads from an [AsyncRead] and yields decoded messages as a [Stream].
+//! [FrameSink] encodes messages into a buffer and writes it to an [AsyncWrite] as a [Sink],
+//! never blocking and resuming partial writes where they stopped.
+//!
+//! # Examples
+//! ```
+//! # use tiny_frame::*;
+//! use futures::executor::block_on;
+//! use futures::{SinkExt, StreamExt};
+//! use tiny_frame::stream::{FrameSink, FrameStream};
+//!
+//! block_on(async {
+//!     let mut sink = FrameSink::<DefaultFormat, _>::new(MsgEncoder::new(), Vec::new());
+//!     for data in [&b"hello"[..], b"world"] {
+//!         let msg = Msg {
+//!             id: 0,
+//!             is_response: false,
+//!             msg_type: 0x30,
+//!             data: data.to_vec(),
+//!         };
+//!         sink.send(msg).await.unwrap();
+//!     }
+//!
+//!     let bytes = sink.into_inner();
+//!     let mut stream = FrameStream::<DefaultFormat, _>::new(MsgDecoder::new(), &bytes[..]);
+//!     assert_eq!(stream.next().await.unwrap().unwrap().data, b"hello");
+//!     assert_eq!(stream.next().await.unwrap().unwrap().data, b"world");
+//!     assert!(stream.next().await.is_none());
+//! });
+//! ```
+
+use crate::{FrameFormat, Msg, MsgDecoder, MsgEncoder};
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const READ_BUFFER_SIZE: usize = 256;
+
+/// A stream of messages decoded from an [AsyncRead].
+///
+/// Frames that fail to decode are dropped, as with [MsgDecoder::accept]. Read errors are yielded
+/// as items; the stream ends when the reader reaches the end of its input.
+pub struct FrameStream<F: FrameFormat, R> {
+    decoder: MsgDecoder<F>,
+    reader: R,
+    buf: [u8; READ_BUFFER_SIZE],
+    pos: usize,
+    len: usize,
+}
+
+impl<F: FrameFormat, R: AsyncRead + Unpin> FrameStream<F, R> {
+    /// Creates a new stream.
+    pub fn new(decoder: MsgDecoder<F>, reader: R) -> FrameStream<F, R> {
+        FrameStream {
+            decoder,
+            reader,
+            buf: [0; READ_BUFFER_SIZE],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the decoder.
+    pub fn decoder(&mut self) -> &mut MsgDecoder<F> {
+        &mut self.decoder
+    }
+
+    /// Returns the reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns the reader. Reading from it directly will corrupt the stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the reader, discarding buffered input.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+// FrameStream holds no pinned data
+impl<F: FrameFormat, R: Unpin> Unpin for FrameStream<F, R> {}
+
+impl<F: FrameFormat, R: AsyncRead + Unpin> Stream for FrameStream<F, R> {
+    type Item = io::Result<Msg<F>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<io::Result<Msg<F>>>> {
+        let this = self.get_mut();
+        loop {
+            while this.pos < this.len {
+                let byte = this.buf[this.pos];
+                this.pos += 1;
+                if let Some(msg) = this.decoder.accept(byte) {
+                    return Poll::Ready(Some(Ok(msg)));
+                }
+            }
+
+            match Pin::new(&mut this.reader).poll_read(cx, &mut this.buf) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(None),
+                Poll::Ready(Ok(len)) => {
+                    this.pos = 0;
+                    this.len = len;
+                }
+                Poll::Ready(Err(err)) if err.kind() == io::ErrorKind::Interrupted => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<F: FrameFormat, R: fmt::Debug> fmt::Debug for FrameStream<F, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FrameStream")
+            .field("reader", &self.reader)
+            .field("buffered", &(self.len - self.pos))
+            .finish_non_exhaustive()
+    }
+}
+
+/// A sink of messages encoded into an [AsyncWrite].
+///
+/// Messages are encoded into a buffer by [Sink::start_send] or [encode](Self::encode), and
+/// written by [Sink::poll_ready], [Sink::poll_flush] and [Sink::poll_close]. A message is only
+/// accepted once the previous ones were written completely, so the buffer never holds more than
+/// one message when used as a [Sink].
+pub struct FrameSink<F: FrameFormat, W> {
+    encoder: MsgEncoder<F>,
+    writer: W,
+    buf: Vec<u8>,
+    written: usize,
+}
+
+impl<F: FrameFormat, W: AsyncWrite + Unpin> FrameSink<F, W> {
+    /// Creates a new sink.
+    pub fn new(encoder: MsgEncoder<F>, writer: W) -> FrameSink<F, W> {
+        FrameSink {
+            encoder,
+            writer,
+            buf: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Returns the encoder.
+    pub fn encoder(&mut self) -> &mut MsgEncoder<F> {
+        &mut self.encoder
+    }
+
+    /// Returns the writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns the writer. Writing to it directly may interleave with buffered frames.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the writer, discarding frames that were not written yet.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Encodes a message into the buffer. If the message is not a response, a new ID will be
+    /// assigned. Returns the ID of the message.
+    ///
+    /// Unlike [Sink::start_send], this does not require the buffer to be empty.
+    pub fn encode(&mut self, msg: Msg<F>) -> io::Result<F::Id> {
+        if self.written == self.buf.len() {
+            self.buf.clear();
+            self.written = 0;
+        }
+        msg.encode(&mut self.buf, &mut self.encoder)
+    }
+
+    /// Writes buffered frames until the buffer is empty or the writer is not ready.
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.buf.len() {
+            match Pin::new(&mut self.writer).poll_write(cx, &self.buf[self.written..]) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                Poll::Ready(Ok(len)) => self.written += len,
+                Poll::Ready(Err(err)) if err.kind() == io::ErrorKind::Interrupted => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        self.buf.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+// FrameSink holds no pinned data
+impl<F: FrameFormat, W: Unpin> Unpin for FrameSink<F, W> {}
+
+impl<F: FrameFormat, W: AsyncWrite + Unpin> Sink<Msg<F>> for FrameSink<F, W> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_write_buf(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, msg: Msg<F>) -> io::Result<()> {
+        self.get_mut().encode(msg).map(|_| ())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_write_buf(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.writer).poll_flush(cx),
+            poll => poll,
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_write_buf(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.writer).poll_close(cx),
+            poll => poll,
+        }
+    }
+}
+
+impl<F: FrameFormat, W: fmt::Debug> fmt::Debug for FrameSink<F, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FrameSink")
+            .field("encoder", &self.encoder)
+            .field("writer", &self.writer)
+            .field("buffered", &(self.buf.len() - self.written))
+            .finish()
+    }
+}
@@ -0,0 +1,111 @@
+//! Tests of the `tinyframe-bridge` binary over a pseudo-terminal pair.
+#![cfg(all(feature = "serial", target_os = "linux"))]
+
+use serialport::{SerialPort, TTYPort};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use tiny_frame::*;
+
+/// Kills the bridge when a test ends, even if it fails.
+struct Bridge(Child);
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts a bridge on the slave side of a pty pair and connects a client to it. The master side
+/// plays the device.
+fn start(args: &[&str]) -> (Bridge, TTYPort, TcpStream) {
+    let (mut device, slave) = TTYPort::pair().expect("failed to open pty pair");
+    device.set_timeout(Duration::from_secs(5)).unwrap();
+    let path = slave.name().expect("pty has no name");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tinyframe-bridge"))
+        .args(args)
+        .arg(&path)
+        .arg("127.0.0.1:0")
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start the bridge");
+
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    // keep reading the log so that the bridge does not fail writing it
+    thread::spawn(move || io::copy(&mut stderr, &mut io::sink()));
+    let addr = line
+        .trim()
+        .rsplit(' ')
+        .next()
+        .expect("bridge did not print its address");
+
+    let client = TcpStream::connect(addr).expect("failed to connect to the bridge");
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    (Bridge(child), device, client)
+}
+
+fn frame(msg_type: u8, data: &[u8]) -> Vec<u8> {
+    let msg: Msg<DefaultFormat> = Msg {
+        id: 0,
+        is_response: false,
+        msg_type,
+        data: data.to_vec(),
+    };
+    let mut bytes = Vec::new();
+    msg.encode(&mut bytes, &mut MsgEncoder::new()).unwrap();
+    bytes
+}
+
+fn read_msg<R: Read>(input: &mut R) -> Msg<DefaultFormat> {
+    let mut decoder = MsgDecoder::new();
+    let mut byte = [0];
+    loop {
+        input.read_exact(&mut byte).expect("failed to read");
+        if let Some(msg) = decoder.accept(byte[0]) {
+            return msg;
+        }
+    }
+}
+
+#[test]
+fn relays_both_directions() {
+    let (_bridge, mut device, mut client) = start(&[]);
+
+    client.write_all(&frame(0x10, b"to device")).unwrap();
+    assert_eq!(read_msg(&mut device).data, b"to device");
+
+    device.write_all(&frame(0x11, b"to client")).unwrap();
+    assert_eq!(read_msg(&mut client).data, b"to client");
+}
+
+#[test]
+fn validation_drops_corrupted_frames() {
+    let (_bridge, mut device, mut client) = start(&["--validate"]);
+
+    let mut corrupted = frame(0x10, b"corrupted");
+    corrupted[8] ^= 0xff;
+    client.write_all(&corrupted).unwrap();
+    client.write_all(&frame(0x10, b"valid")).unwrap();
+
+    let mut expected = frame(0x10, b"valid");
+    let mut received = vec![0; expected.len()];
+    device.read_exact(&mut received).unwrap();
+    assert_eq!(received, expected);
+
+    // the same applies to the other direction
+    expected = frame(0x11, b"valid");
+    device.write_all(b"noise").unwrap();
+    device.write_all(&corrupted).unwrap();
+    device.write_all(&expected).unwrap();
+    let mut received = vec![0; expected.len()];
+    client.read_exact(&mut received).unwrap();
+    assert_eq!(received, expected);
+}
@@ -0,0 +1,93 @@
+//! Tests of the C API, called from Rust.
+#![cfg(feature = "capi")]
+
+use std::ptr;
+use tiny_frame::capi::*;
+use tiny_frame::{DefaultFormat, Msg, MsgEncoder};
+
+#[no_mangle]
+extern "C" fn TF_WriteImpl(_tf: *mut TinyFrame, _buff: *const u8, _len: u32) {}
+
+fn msg_with_id(frame_id: TF_ID) -> TF_Msg {
+    TF_Msg {
+        frame_id,
+        is_response: false,
+        r#type: 0,
+        data: ptr::null(),
+        len: 0,
+        userdata: ptr::null_mut(),
+        userdata2: ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn stay(_tf: *mut TinyFrame, _msg: *mut TF_Msg) -> TF_Result {
+    TF_Result::TF_STAY
+}
+
+/// Replaces the listener for ID 1 with one for ID 2, which takes over its slot.
+unsafe fn replace_listener(tf: *mut TinyFrame) {
+    assert!(TF_RemoveIdListener(tf, 1));
+    assert!(TF_AddIdListener(
+        tf,
+        &mut msg_with_id(2),
+        Some(stay),
+        None,
+        0
+    ));
+}
+
+unsafe extern "C" fn replace_and_close(tf: *mut TinyFrame, _msg: *mut TF_Msg) -> TF_Result {
+    replace_listener(tf);
+    TF_Result::TF_CLOSE
+}
+
+unsafe extern "C" fn replace_on_timeout(tf: *mut TinyFrame) -> TF_Result {
+    replace_listener(tf);
+    TF_Result::TF_CLOSE
+}
+
+#[test]
+fn closing_listener_keeps_its_replacement() {
+    unsafe {
+        let tf = TF_Init(TF_Peer::TF_SLAVE);
+        assert!(TF_AddIdListener(
+            tf,
+            &mut msg_with_id(1),
+            Some(replace_and_close),
+            None,
+            0
+        ));
+
+        let msg: Msg<DefaultFormat> = Msg {
+            id: 1,
+            is_response: true,
+            msg_type: 0x10,
+            data: b"reply".to_vec(),
+        };
+        let mut frame = Vec::new();
+        msg.encode(&mut frame, &mut MsgEncoder::new()).unwrap();
+        TF_Accept(tf, frame.as_ptr(), frame.len() as u32);
+
+        assert!(TF_RenewIdListener(tf, 2));
+        TF_DeInit(tf);
+    }
+}
+
+#[test]
+fn timed_out_listener_keeps_its_replacement() {
+    unsafe {
+        let tf = TF_Init(TF_Peer::TF_SLAVE);
+        assert!(TF_AddIdListener(
+            tf,
+            &mut msg_with_id(1),
+            Some(stay),
+            Some(replace_on_timeout),
+            1
+        ));
+
+        TF_Tick(tf);
+
+        assert!(TF_RenewIdListener(tf, 2));
+        TF_DeInit(tf);
+    }
+}
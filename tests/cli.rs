@@ -0,0 +1,121 @@
+//! Tests of the `tinyframe` binary.
+#![cfg(feature = "std")]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use tiny_frame::*;
+
+fn frame(id: u8, msg_type: u8, data: &[u8]) -> Vec<u8> {
+    let msg: Msg<DefaultFormat> = Msg {
+        id,
+        is_response: true,
+        msg_type,
+        data: data.to_vec(),
+    };
+    let mut bytes = Vec::new();
+    msg.encode(&mut bytes, &mut MsgEncoder::new()).unwrap();
+    bytes
+}
+
+fn run(args: &[&str], input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tinyframe"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start tinyframe");
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn decode_hex() {
+    let mut capture = frame(0x85, 0x10, b"hello");
+    let mut corrupted = frame(0x06, 0x11, b"abc");
+    corrupted[8] ^= 0xff;
+    capture.extend_from_slice(&corrupted);
+
+    let hex: Vec<_> = capture
+        .iter()
+        .map(|byte| format!("0x{:02x}", byte))
+        .collect();
+    let output = run(&["decode"], hex.join(", ").as_bytes());
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(
+        lines[0],
+        "frame 1 at offset 0: id 0x85, peer bit 1, type 0x10, 5 bytes, checksum ok"
+    );
+    assert!(lines[1].trim_start().starts_with("0000  68 65 6c 6c 6f "));
+    assert!(lines[1].ends_with("  |hello|"));
+    assert!(lines[2].starts_with(
+        "frame 2 at offset 14: id 0x06, peer bit 0, type 0x11, 3 bytes, data checksum mismatch"
+    ));
+    assert_eq!(lines[4], "2 frames, 1 with checksum errors");
+}
+
+#[test]
+fn decode_binary_with_layout() {
+    let mut encoder = dynamic::DynMsgEncoder::new(dynamic::FrameConfig {
+        sof: None,
+        id_bytes: 2,
+        len_bytes: 1,
+        type_bytes: 2,
+        checksum: dynamic::ChecksumKind::Xor,
+    });
+    let msg = dynamic::DynMsg {
+        id: 0x1234,
+        is_response: true,
+        msg_type: 0xbeef,
+        data: vec![0, 1, 2],
+    };
+    let mut capture = Vec::new();
+    msg.encode(&mut capture, &mut encoder).unwrap();
+
+    let args = [
+        "decode",
+        "--binary",
+        "--sof",
+        "none",
+        "--id-bytes",
+        "2",
+        "--len-bytes",
+        "1",
+        "--type-bytes",
+        "2",
+        "--checksum",
+        "xor",
+    ];
+    let output = run(&args, &capture);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with(
+        "frame 1 at offset 0: id 0x1234, peer bit 0, type 0xbeef, 3 bytes, checksum ok\n"
+    ));
+}
+
+#[test]
+fn invalid_input_is_reported() {
+    let output = run(&["decode"], b"01 0g");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "tinyframe: invalid hex: 0g\n"
+    );
+
+    let output = run(&["decode", "--checksum", "md5"], b"");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn help_explains_peer_bit() {
+    let output = run(&["decode", "--help"], b"");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("a capture cannot tell it apart from a new message"));
+    assert!(stdout.contains("the peer bit of the ID\nis shown"));
+}
@@ -0,0 +1,107 @@
+//! Tests of the UDP datagram transport.
+#![cfg(feature = "std")]
+
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+use tiny_frame::datagram::{DatagramError, UdpTransport};
+use tiny_frame::*;
+
+fn msg(msg_type: u8, data: &[u8]) -> Msg<DefaultFormat> {
+    Msg {
+        id: 0,
+        is_response: false,
+        msg_type,
+        data: data.to_vec(),
+    }
+}
+
+fn client() -> UdpTransport<DefaultFormat> {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut encoder = MsgEncoder::new();
+    encoder.is_master = true;
+    UdpTransport::new(socket, encoder)
+}
+
+#[test]
+fn replies_go_to_the_source() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    thread::spawn(move || {
+        UdpTransport::<DefaultFormat>::new(socket, MsgEncoder::new())
+            .run(|msg, _| Some(msg.create_response(msg.msg_type, msg.data.clone())))
+    });
+
+    let mut clients: Vec<_> = (0..3).map(|_| client()).collect();
+    for (i, client) in clients.iter_mut().enumerate() {
+        client.send_to(msg(i as u8, &[i as u8; 10]), addr).unwrap();
+    }
+    for (i, client) in clients.iter_mut().enumerate() {
+        let (reply, source) = client.recv_from().unwrap();
+        assert_eq!(source, addr);
+        assert_eq!(reply.msg_type, i as u8);
+        assert_eq!(reply.data, [i as u8; 10]);
+    }
+}
+
+#[test]
+fn invalid_datagrams_are_reported() {
+    let mut receiver = client();
+    let addr = receiver.socket().local_addr().unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    let mut frame = Vec::new();
+    msg(0x10, b"data")
+        .encode(&mut frame, &mut MsgEncoder::new())
+        .unwrap();
+
+    // two frames in one datagram
+    sender
+        .send_to(&[&frame[..], &frame[..]].concat(), addr)
+        .unwrap();
+    let (result, _) = receiver.try_recv_from().unwrap();
+    assert_eq!(result, Err(DatagramError::TrailingBytes(frame.len())));
+
+    sender.send_to(&frame[1..], addr).unwrap();
+    let (result, _) = receiver.try_recv_from().unwrap();
+    assert_eq!(result, Err(DatagramError::MissingSof));
+
+    // recv_from skips invalid datagrams
+    sender.send_to(&frame[..5], addr).unwrap();
+    sender.send_to(&frame, addr).unwrap();
+    let (received, source) = receiver.recv_from().unwrap();
+    assert_eq!(received.data, b"data");
+    assert_eq!(source, sender.local_addr().unwrap());
+}
+
+#[test]
+fn run_waits_out_read_timeouts() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_millis(5)))
+        .unwrap();
+    let addr = socket.local_addr().unwrap();
+    thread::spawn(move || {
+        UdpTransport::<DefaultFormat>::new(socket, MsgEncoder::new())
+            .run(|msg, _| Some(msg.create_response(msg.msg_type, msg.data.clone())))
+    });
+
+    // the server has timed out several times before the request arrives
+    thread::sleep(Duration::from_millis(50));
+    let mut client = client();
+    client.send_to(msg(0x20, b"late"), addr).unwrap();
+    let (reply, _) = client.recv_from().unwrap();
+    assert_eq!(reply.data, b"late");
+}
+
+#[test]
+fn run_fails_on_non_blocking_socket() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_nonblocking(true).unwrap();
+    let mut transport = UdpTransport::<DefaultFormat>::new(socket, MsgEncoder::new());
+    let err = transport.run(|_, _| None).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+}
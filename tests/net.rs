@@ -0,0 +1,264 @@
+//! Tests of the TCP and Unix socket transports.
+#![cfg(feature = "std")]
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tiny_frame::net::{
+    tcp_connector, Backoff, FrameListener, Listen, ReconnectingClient, StreamTransport,
+};
+use tiny_frame::*;
+
+fn msg(msg_type: u8, data: &[u8]) -> Msg<DefaultFormat> {
+    Msg {
+        id: 0,
+        is_response: false,
+        msg_type,
+        data: data.to_vec(),
+    }
+}
+
+fn master() -> MsgEncoder<DefaultFormat> {
+    let mut encoder = MsgEncoder::new();
+    encoder.is_master = true;
+    encoder
+}
+
+#[test]
+fn clients_have_separate_state() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let listener = FrameListener::<DefaultFormat, _>::new(listener, false);
+
+    // answer every message with a new message, which gets an ID from the client's encoder
+    thread::spawn(move || {
+        listener.serve(
+            |msg| Some(self::msg(msg.msg_type, &msg.data)),
+            |err| panic!("{}", err),
+        )
+    });
+
+    let mut clients: Vec<_> = (0..3)
+        .map(|_| StreamTransport::new(TcpStream::connect(addr).unwrap(), master()))
+        .collect();
+
+    for round in 0..3u8 {
+        for (i, client) in clients.iter_mut().enumerate() {
+            client.send(msg(i as u8, &[round])).unwrap();
+        }
+        for (i, client) in clients.iter_mut().enumerate() {
+            let reply = client.recv().unwrap().unwrap();
+            assert_eq!(reply.id, round);
+            assert_eq!(reply.msg_type, i as u8);
+            assert_eq!(reply.data, [round]);
+        }
+    }
+}
+
+#[test]
+fn frames_split_across_reads() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let listener = FrameListener::<DefaultFormat, _>::new(listener, false);
+        let mut client = listener.accept().unwrap();
+        client.recv().unwrap().unwrap()
+    });
+
+    let mut frame = Vec::new();
+    msg(0x40, &[7; 300])
+        .encode(&mut frame, &mut master())
+        .unwrap();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    for chunk in frame.chunks(7) {
+        stream.write_all(chunk).unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    assert_eq!(server.join().unwrap().data, [7; 300]);
+}
+
+/// A listener that returns the given results in order.
+struct ScriptedListener(Mutex<Vec<io::Result<FailingStream>>>);
+
+impl Listen for ScriptedListener {
+    type Stream = FailingStream;
+
+    fn accept_stream(&self) -> io::Result<FailingStream> {
+        self.0.lock().unwrap().remove(0)
+    }
+}
+
+/// A connection whose reads fail.
+struct FailingStream;
+
+impl Read for FailingStream {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("read failed"))
+    }
+}
+
+impl Write for FailingStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn serve_reports_errors() {
+    let listener = ScriptedListener(Mutex::new(vec![
+        Err(io::ErrorKind::ConnectionAborted.into()),
+        Ok(FailingStream),
+        Err(io::ErrorKind::InvalidInput.into()),
+    ]));
+    let listener = FrameListener::<DefaultFormat, _>::new(listener, false);
+
+    // the aborted connection does not stop the server, but the last error does
+    let (errors, received) = mpsc::channel();
+    let result = listener.serve(|_| None, move |err| errors.send(err.kind()).unwrap());
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    let timeout = Duration::from_secs(5);
+    assert_eq!(
+        received.recv_timeout(timeout),
+        Ok(io::ErrorKind::ConnectionAborted)
+    );
+    // reported by the client thread
+    assert_eq!(received.recv_timeout(timeout), Ok(io::ErrorKind::Other));
+}
+
+#[test]
+fn run_fails_on_non_blocking_stream() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    stream.set_nonblocking(true).unwrap();
+
+    let mut transport = StreamTransport::<DefaultFormat, _>::new(stream, master());
+    assert_eq!(transport.recv().unwrap(), None);
+    let err = transport.run(|_| None).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+}
+
+#[test]
+fn run_waits_out_read_timeouts() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_millis(5)))
+        .unwrap();
+
+    let server = thread::spawn(move || {
+        let mut transport = StreamTransport::<DefaultFormat, _>::new(stream, MsgEncoder::new());
+        let mut received = Vec::new();
+        transport
+            .run(|msg| {
+                received.push(msg.data);
+                None
+            })
+            .map(|()| received)
+    });
+
+    // the server has timed out several times before the message arrives
+    thread::sleep(Duration::from_millis(50));
+    let mut frame = Vec::new();
+    msg(0x30, b"late")
+        .encode(&mut frame, &mut master())
+        .unwrap();
+    client.write_all(&frame).unwrap();
+    drop(client);
+
+    assert_eq!(server.join().unwrap().unwrap(), [b"late"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_socket() {
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let path = std::env::temp_dir().join(format!("tiny_frame_test_{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener =
+        FrameListener::<DefaultFormat, _>::new(UnixListener::bind(&path).unwrap(), false);
+    thread::spawn(move || {
+        listener.serve(
+            |msg| Some(msg.create_response(msg.msg_type, msg.data.clone())),
+            |err| panic!("{}", err),
+        )
+    });
+
+    let mut client = StreamTransport::new(UnixStream::connect(&path).unwrap(), master());
+    let id = client.send(msg(0x50, b"unix")).unwrap();
+    let reply = client.recv().unwrap().unwrap();
+    assert_eq!(reply.id, id);
+    assert_eq!(reply.data, b"unix");
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn client_reconnects() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // the server drops the first connection after one message and keeps the second
+    let server = thread::spawn(move || {
+        let listener = FrameListener::<DefaultFormat, _>::new(listener, false);
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let mut client = listener.accept().unwrap();
+            let msg = client.recv().unwrap().unwrap();
+            ids.push(msg.id);
+            client.send(msg.create_response(0, vec![])).unwrap();
+        }
+        ids
+    });
+
+    let backoff = Backoff {
+        initial: Duration::from_millis(1),
+        max_attempts: Some(100),
+        ..Backoff::default()
+    };
+    let mut client = ReconnectingClient::new(tcp_connector(addr), master(), backoff);
+
+    let first = client.send(msg(0x60, b"first")).unwrap();
+    assert!(client.recv().unwrap().is_some());
+    // the server closed the connection
+    assert!(client.recv().is_err());
+    assert!(!client.is_connected());
+
+    let second = client.send(msg(0x60, b"second")).unwrap();
+    assert!(client.recv().unwrap().is_some());
+    assert_eq!(server.join().unwrap(), [first, second]);
+    assert_ne!(first, second);
+}
+
+#[test]
+fn connect_gives_up() {
+    // bind and drop a listener to get a port that refuses connections
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let backoff = Backoff {
+        initial: Duration::from_millis(1),
+        max_attempts: Some(3),
+        ..Backoff::default()
+    };
+    let mut attempts = 0;
+    let connect = || {
+        attempts += 1;
+        TcpStream::connect(addr)
+    };
+    let mut client = ReconnectingClient::<DefaultFormat, _, _>::new(connect, master(), backoff);
+    assert!(client.connect().is_err());
+    drop(client);
+    assert_eq!(attempts, 3);
+}
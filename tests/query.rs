@@ -0,0 +1,69 @@
+//! Tests of queries as futures.
+#![cfg(feature = "futures")]
+
+use futures_executor::block_on;
+use std::io;
+use tiny_frame::peer::{Event, Peer};
+use tiny_frame::query::AsyncPeer;
+use tiny_frame::*;
+
+fn ping() -> Msg<DefaultFormat> {
+    Msg {
+        id: 0,
+        is_response: false,
+        msg_type: 0x70,
+        data: b"ping".to_vec(),
+    }
+}
+
+/// Answers all queries the slave receives from the master.
+fn answer_all(master: &AsyncPeer<DefaultFormat>, slave: &mut Peer<DefaultFormat>) {
+    while let Some(frame) = master.take_transmit() {
+        slave.handle_input(&frame);
+    }
+    while let Some(event) = slave.poll_event() {
+        if let Event::Message(msg) = event {
+            let data = vec![msg.id];
+            slave.send(msg.create_response(0x71, data)).unwrap();
+        }
+    }
+    while let Some(frame) = slave.poll_transmit() {
+        master.handle_input(&frame);
+    }
+}
+
+#[test]
+fn ids_of_live_queries_are_not_reused() {
+    let master = AsyncPeer::<DefaultFormat>::new(Peer::new(true));
+    let mut slave = Peer::<DefaultFormat>::new(false);
+
+    // the first query finishes, but is not polled until the IDs have wrapped around
+    let first = master.query(ping());
+    let first_id = first.id().unwrap();
+    answer_all(&master, &mut slave);
+
+    for _ in 0..300 {
+        let query = master.query(ping());
+        assert_ne!(query.id(), Some(first_id));
+        answer_all(&master, &mut slave);
+        let reply = block_on(query).unwrap();
+        assert_ne!(reply.data, [first_id]);
+    }
+
+    let reply = block_on(first).unwrap();
+    assert_eq!(reply.data, [first_id]);
+}
+
+#[test]
+fn query_fails_when_all_ids_are_in_use() {
+    let master = AsyncPeer::<DefaultFormat>::new(Peer::new(true));
+    let queries: Vec<_> = (0..128).map(|_| master.query(ping())).collect();
+    assert!(queries.iter().all(|query| query.id().is_some()));
+
+    let err = block_on(master.query(ping())).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+
+    // dropping a query frees its ID
+    drop(queries);
+    assert!(master.query(ping()).id().is_some());
+}
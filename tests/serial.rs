@@ -0,0 +1,156 @@
+//! Tests of the serial transport over a pseudo-terminal pair, so that no hardware is needed.
+#![cfg(all(feature = "serial", target_os = "linux"))]
+
+use serialport::{SerialPort, TTYPort};
+use std::io::{Read, Write};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tiny_frame::number::FrameId;
+use tiny_frame::serial::{Parity, SerialConfig, SerialTransport};
+use tiny_frame::*;
+
+/// Opens a pty pair and a transport on its slave side. The master side plays the remote device.
+fn open(config: &SerialConfig) -> (TTYPort, SerialTransport<DefaultFormat>) {
+    let (mut master, slave) = TTYPort::pair().expect("failed to open pty pair");
+    master.set_timeout(Duration::from_secs(5)).unwrap();
+    let path = slave.name().expect("pty has no name");
+
+    let mut encoder = MsgEncoder::new();
+    encoder.is_master = true;
+    let transport = SerialTransport::open(&path, config, encoder).expect("failed to open pty");
+    (master, transport)
+}
+
+fn read_msg(port: &mut TTYPort) -> Msg<DefaultFormat> {
+    let mut decoder = MsgDecoder::new();
+    let mut byte = [0];
+    loop {
+        port.read_exact(&mut byte).expect("failed to read from pty");
+        if let Some(msg) = decoder.accept(byte[0]) {
+            return msg;
+        }
+    }
+}
+
+fn msg(msg_type: u8, data: &[u8]) -> Msg<DefaultFormat> {
+    Msg {
+        id: 0,
+        is_response: false,
+        msg_type,
+        data: data.to_vec(),
+    }
+}
+
+#[test]
+fn send_and_recv() {
+    let config = SerialConfig {
+        baud_rate: 9600,
+        parity: Parity::Even,
+        ..SerialConfig::default()
+    };
+    let (mut remote, mut transport) = open(&config);
+
+    let id = transport.send(msg(0x10, b"hello")).unwrap();
+    let received = read_msg(&mut remote);
+    assert_eq!(received.id, id);
+    assert!(received.id.has_master_peer_bit());
+    assert_eq!(received.data, b"hello");
+
+    let mut frame = Vec::new();
+    let reply = received.create_response(0x11, b"world".to_vec());
+    reply.encode(&mut frame, &mut MsgEncoder::new()).unwrap();
+    remote.write_all(&frame).unwrap();
+
+    let received = transport.recv().unwrap().expect("no message received");
+    assert_eq!(received.id, id);
+    assert_eq!(received.data, b"world");
+}
+
+#[test]
+fn run_answers_queries() {
+    let (mut remote, mut transport) = open(&SerialConfig::default());
+    let (idle, is_idle) = mpsc::channel();
+    let server = thread::spawn(move || {
+        transport.run(|msg| {
+            if msg.msg_type == 0x2f {
+                idle.send(()).unwrap();
+                return None;
+            }
+            let mut data = msg.data.clone();
+            data.reverse();
+            Some(msg.create_response(msg.msg_type + 1, data))
+        })
+    });
+
+    let mut encoder = MsgEncoder::<DefaultFormat>::new();
+    for i in 0..20u8 {
+        let mut frame = Vec::new();
+        let id = msg(0x20, &[i, i + 1, i + 2])
+            .encode(&mut frame, &mut encoder)
+            .unwrap();
+        remote.write_all(&frame).unwrap();
+
+        let reply = read_msg(&mut remote);
+        assert_eq!(reply.id, id);
+        assert_eq!(reply.msg_type, 0x21);
+        assert_eq!(reply.data, [i + 2, i + 1, i]);
+    }
+
+    // once the last reply has been sent, closing the remote side hangs up the pty, which ends
+    // the loop without an error
+    let mut frame = Vec::new();
+    msg(0x2f, &[]).encode(&mut frame, &mut encoder).unwrap();
+    remote.write_all(&frame).unwrap();
+    is_idle.recv_timeout(Duration::from_secs(5)).unwrap();
+    drop(remote);
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn partial_frame_is_discarded_after_timeout() {
+    let config = SerialConfig {
+        timeout: Duration::from_millis(20),
+        ..SerialConfig::default()
+    };
+    let (mut remote, mut transport) = open(&config);
+
+    let mut frame = Vec::new();
+    msg(0x30, b"lost")
+        .encode(&mut frame, &mut MsgEncoder::new())
+        .unwrap();
+    remote.write_all(&frame[..frame.len() / 2]).unwrap();
+    assert_eq!(transport.recv().unwrap(), None);
+
+    let mut frame = Vec::new();
+    msg(0x31, b"complete")
+        .encode(&mut frame, &mut MsgEncoder::new())
+        .unwrap();
+    remote.write_all(&frame).unwrap();
+    let received = transport.recv().unwrap().expect("no message received");
+    assert_eq!(received.msg_type, 0x31);
+    assert_eq!(received.data, b"complete");
+}
+
+#[test]
+fn sender_writes_from_other_threads() {
+    let (mut remote, transport) = open(&SerialConfig::default());
+
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let sender = transport.sender();
+            thread::spawn(move || {
+                for _ in 0..5 {
+                    sender.send(msg(i, b"data")).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let mut ids: Vec<_> = (0..20).map(|_| read_msg(&mut remote).id & 0x7f).collect();
+    ids.sort();
+    assert_eq!(ids, (0..20).collect::<Vec<u8>>());
+}